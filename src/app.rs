@@ -1,4 +1,5 @@
 use crate::audio::AudioManager;
+use crate::input::{InputThread, TimedEvent};
 use crate::models::Song;
 use crate::states::State::{Playing, Welcome};
 use crate::states::collection::CollectionState;
@@ -6,16 +7,18 @@ use crate::states::playing::{PlayingPhase, PlayingState};
 use crate::states::result::ResultState;
 use crate::states::welcome::WelcomeState;
 use crate::states::*;
-use ratatui::crossterm::event::{self, Event};
+use ratatui::crossterm::event::Event;
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::io::Stdout;
 use std::time::{Duration, Instant};
 use crate::config::GlobalConfig;
+use log::error;
 
 pub struct App {
     is_running: bool,
     state: State,
     context: AppContext,
+    input: InputThread,
 }
 
 pub struct AppContext {
@@ -34,6 +37,7 @@ impl App {
                 audio: AudioManager::new(),
                 global_config,
             },
+            input: InputThread::spawn(),
         }
     }
     pub fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
@@ -56,10 +60,14 @@ impl App {
 
             terminal.draw(|f| self.state.draw(&self.context, f))?;
 
-            // 轮询检测是否有按键事件
-            if event::poll(Duration::from_millis(self.context.global_config.poll_period))? {
-                if let Event::Key(key) = event::read()? {
-                    let action = self.state.handle_input(&self.context, key);
+            // 等待输入线程的事件，一次处理完排队的全部按键
+            let events = self.input.drain(Duration::from_millis(self.context.global_config.poll_period));
+            for TimedEvent { event, at } in events {
+                if !self.is_running {
+                    break;
+                }
+                if let Event::Key(key) = event {
+                    let action = self.state.handle_input(&self.context, key, at);
                     self.resolve_action(action);
                 }
             }
@@ -85,12 +93,21 @@ impl App {
                 self.context.audio.stop();
                 self.state = State::Collection(CollectionState::new(self.context.songs.len()))
             }
-            StateAction::GoToPlaying { song, chart } => {
-                self.state = Playing(PlayingState::new(song, &chart, &self.context));
+            StateAction::GoToPlaying { song, chart, modifiers, rate } => {
+                // keysound 路径相对于歌曲目录 (音频文件所在目录)
+                if let Some(dir) = song.asset.audio.get_local_path().and_then(|p| p.parent().map(|d| d.to_path_buf())) {
+                    self.context.audio.load_keysounds(&dir, chart.keysounds());
+                }
+                // 失败时留在选曲界面
+                match PlayingState::new(song, &chart, modifiers, rate, &self.context) {
+                    Ok(playing) => self.state = Playing(playing),
+                    Err(e) => error!("Failed to start chart: {e}"),
+                }
             }
-            StateAction::StartAudio { song_asset } => {
+            StateAction::StartAudio { song_asset, rate } => {
                 if let Some(path) = song_asset.audio.get_local_path() {
-                    self.context.audio.play_music(path).unwrap();
+                    let preserve_pitch = self.context.global_config.playing.preserve_pitch;
+                    self.context.audio.play_music(path, rate, preserve_pitch).unwrap();
                 }
             }
             StateAction::TogglePause => {
@@ -1,9 +1,14 @@
+mod stretch;
+
 use rodio::buffer::SamplesBuffer;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use log::warn;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use std::time::{Duration, Instant};
+use stretch::TimeStretch;
 
 pub struct AudioManager {
     _stream: OutputStream,
@@ -11,10 +16,13 @@ pub struct AudioManager {
     sink: Sink,
     // --- 新增：音效缓存 ---
     hit_data: Option<(Vec<f32>, u16, u32)>,
+    // 当前谱面的 keysound，按谱面里写的文件名索引
+    keysounds: HashMap<String, (Vec<f32>, u16, u32)>,
 
     start_instant: Option<Instant>,
     accumulated_time: Duration,
     is_playing: bool,
+    rate: f64, // 播放倍速
 }
 impl AudioManager {
     pub fn new() -> Self {
@@ -30,15 +38,17 @@ impl AudioManager {
             handle,
             sink,
             hit_data: Some(hit_data),
+            keysounds: HashMap::new(),
             start_instant: None,
             accumulated_time: Duration::ZERO,
             is_playing: false,
+            rate: 1.0,
         }
     }
 
 
     // 辅助函数：从文件加载
-    fn load_hit_file(path: &str) -> Option<(Vec<f32>, u16, u32)> {
+    fn load_hit_file<T: AsRef<Path>>(path: T) -> Option<(Vec<f32>, u16, u32)> {
         File::open(path).ok().and_then(|file| {
             let decoder = Decoder::new(BufReader::new(file)).ok()?;
             let channels = decoder.channels();
@@ -73,14 +83,22 @@ impl AudioManager {
     }
 
 
-    pub fn play_music<T>(&mut self, path: T) -> anyhow::Result<()>
+    /// 以 `rate` 倍速播放；`preserve_pitch` 时做时间伸缩保持音高，否则直接变速变调
+    pub fn play_music<T>(&mut self, path: T, rate: f64, preserve_pitch: bool) -> anyhow::Result<()>
     where T: AsRef<Path>
     {
         let file = BufReader::new(File::open(path)?);
         let source = Decoder::new(file)?;
 
         self.sink.stop();
-        self.sink.append(source);
+        if rate == 1.0 {
+            self.sink.append(source);
+        } else if preserve_pitch {
+            self.sink.append(TimeStretch::new(source.convert_samples(), rate));
+        } else {
+            self.sink.append(source.speed(rate as f32));
+        }
+        self.rate = rate;
 
         // 重置计时器
         self.start_instant = Some(Instant::now());
@@ -93,12 +111,39 @@ impl AudioManager {
 
 
     pub fn play_hit_effect(&self) {
-        if let Some((samples, channels, rate)) = &self.hit_data {
-            // 直接从内存构建 buffer，省去每一击的解码开销
-            let source = SamplesBuffer::new(*channels, *rate, samples.clone());
-            let _ = self.handle.play_raw(source.convert_samples());
+        if let Some(data) = &self.hit_data {
+            self.play_samples(data);
+        }
+    }
+
+    /// 预先解码谱面用到的全部 keysound，替换上一张谱面的缓存
+    pub fn load_keysounds<'a>(&mut self, dir: &Path, names: impl IntoIterator<Item = &'a str>) {
+        self.keysounds.clear();
+        for name in names {
+            let Some(path) = keysound_path(dir, name) else {
+                warn!("Skipping keysound {name:?} outside the song directory");
+                continue;
+            };
+            match Self::load_hit_file(&path) {
+                Some(data) => {
+                    self.keysounds.insert(name.to_string(), data);
+                }
+                None => warn!("Failed to load keysound {path:?}"),
+            }
         }
     }
+
+    pub fn play_keysound(&self, name: &str) {
+        if let Some(data) = self.keysounds.get(name) {
+            self.play_samples(data);
+        }
+    }
+
+    fn play_samples(&self, (samples, channels, rate): &(Vec<f32>, u16, u32)) {
+        // 直接从内存构建 buffer，省去每一击的解码开销
+        let source = SamplesBuffer::new(*channels, *rate, samples.clone());
+        let _ = self.handle.play_raw(source.convert_samples());
+    }
     pub fn pause(&mut self) {
         if self.is_playing {
             if let Some(start) = self.start_instant {
@@ -118,15 +163,18 @@ impl AudioManager {
         }
     }
 
+    /// 当前播放到的歌曲位置 (原速下的时间，与倍速无关)
     pub fn get_pos(&self) -> Duration {
-        if !self.is_playing {
-            return self.accumulated_time;
-        }
+        self.pos_at(Instant::now())
+    }
 
-        match self.start_instant {
-            Some(start) => self.accumulated_time + start.elapsed(),
-            None => self.accumulated_time,
-        }
+    /// `at` 时刻的歌曲位置，用于把输入线程的时间戳换算成歌曲时间
+    pub fn pos_at(&self, at: Instant) -> Duration {
+        let played = match self.start_instant {
+            Some(start) if self.is_playing => self.accumulated_time + at.saturating_duration_since(start),
+            _ => self.accumulated_time,
+        };
+        played.mul_f64(self.rate)
     }
 
     pub fn stop(&mut self) {
@@ -139,4 +187,26 @@ impl AudioManager {
     pub fn is_finished(&self) -> bool {
         self.sink.empty()
     }
-}
\ No newline at end of file
+}
+
+/// 谱面里的 keysound 只能是歌曲目录下的相对路径，绝对路径和 `..` 返回 None
+fn keysound_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    Path::new(name).components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        .then(|| dir.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keysound_path_stays_in_song_dir() {
+        let dir = Path::new("songs/a");
+        assert_eq!(keysound_path(dir, "kick.wav"), Some(dir.join("kick.wav")));
+        assert_eq!(keysound_path(dir, "./sounds/kick.wav"), Some(dir.join("./sounds/kick.wav")));
+        for name in ["/etc/passwd", "../b/kick.wav", "sounds/../../kick.wav"] {
+            assert_eq!(keysound_path(dir, name), None, "{name}");
+        }
+    }
+}
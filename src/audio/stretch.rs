@@ -0,0 +1,214 @@
+use rodio::Source;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// 分析/合成帧长 (每声道采样数)
+const FRAME: usize = 2048;
+/// 合成步长，Hann 窗在 50% 重叠时叠加恒为 1
+const HOP: usize = FRAME / 2;
+/// WSOLA 搜索范围
+const TOLERANCE: usize = 512;
+/// 相关性计算的抽样步长，降低开销
+const CORR_STEP: usize = 4;
+
+/// Changes playback speed by `rate` while keeping the pitch (WSOLA).
+///
+/// Frames are read from the input every `HOP * rate` samples, shifted by up
+/// to `TOLERANCE` to line up with the previous frame's continuation, and
+/// overlap-added every `HOP` samples.
+pub struct TimeStretch<S: Source<Item = f32>> {
+    source: S,
+    channels: usize,
+    sample_rate: u32,
+    rate: f64,
+    window: Vec<f32>,
+    input: VecDeque<f32>, // interleaved, input[0] 是第 input_start 帧
+    input_start: usize,
+    exhausted: bool,
+    steps: usize,              // 已合成的帧数
+    prev_start: Option<usize>, // 上一帧实际取用的位置
+    overlap: Vec<f32>,         // 尚未完成叠加的 FRAME 帧输出
+    output: VecDeque<f32>,
+    finished: bool,
+}
+
+impl<S: Source<Item = f32>> TimeStretch<S> {
+    pub fn new(source: S, rate: f64) -> Self {
+        let channels = source.channels().max(1) as usize;
+        let sample_rate = source.sample_rate();
+        let window = (0..FRAME)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME as f32).cos())
+            .collect();
+        Self {
+            source,
+            channels,
+            sample_rate,
+            rate,
+            window,
+            input: VecDeque::new(),
+            input_start: 0,
+            exhausted: false,
+            steps: 0,
+            prev_start: None,
+            overlap: vec![0.0; FRAME * channels],
+            output: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn input_frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    /// 读入直到缓冲区覆盖第 `frame` 帧之前的全部输入
+    fn fill_until(&mut self, frame: usize) {
+        while !self.exhausted && self.input_start + self.input_frames() < frame {
+            for _ in 0..self.channels {
+                match self.source.next() {
+                    Some(s) => self.input.push_back(s),
+                    None => {
+                        self.exhausted = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 单声道混合后的采样，越界补 0
+    fn mono(&self, frame: usize) -> f32 {
+        if frame < self.input_start || frame >= self.input_start + self.input_frames() {
+            return 0.0;
+        }
+        let base = (frame - self.input_start) * self.channels;
+        (0..self.channels).map(|c| self.input[base + c]).sum()
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        if frame < self.input_start || frame >= self.input_start + self.input_frames() {
+            return 0.0;
+        }
+        self.input[(frame - self.input_start) * self.channels + channel]
+    }
+
+    /// 在期望位置附近找与上一帧自然延续最相似的起点
+    fn best_start(&self, expected: usize) -> usize {
+        let Some(prev) = self.prev_start else { return expected };
+        let natural = prev + HOP;
+        let lo = expected.saturating_sub(TOLERANCE);
+        let hi = expected + TOLERANCE;
+
+        let mut best = (f32::NEG_INFINITY, expected);
+        for candidate in (lo..=hi).step_by(CORR_STEP) {
+            let corr: f32 = (0..HOP)
+                .step_by(CORR_STEP)
+                .map(|i| self.mono(natural + i) * self.mono(candidate + i))
+                .sum();
+            if corr > best.0 {
+                best = (corr, candidate);
+            }
+        }
+        best.1
+    }
+
+    /// 合成 HOP 帧输出，输入耗尽且已无剩余时返回 false
+    fn synthesize(&mut self) -> bool {
+        let expected = (self.steps as f64 * HOP as f64 * self.rate).round() as usize;
+        self.fill_until(expected + TOLERANCE + FRAME);
+        if self.exhausted && expected >= self.input_start + self.input_frames() {
+            // 把最后一段重叠输出冲掉
+            self.output.extend(self.overlap.drain(..HOP * self.channels));
+            return false;
+        }
+
+        let start = self.best_start(expected);
+        for i in 0..FRAME {
+            for c in 0..self.channels {
+                self.overlap[i * self.channels + c] += self.sample(start + i, c) * self.window[i];
+            }
+        }
+
+        self.output.extend(self.overlap.drain(..HOP * self.channels));
+        self.overlap.resize(FRAME * self.channels, 0.0);
+
+        self.prev_start = Some(start);
+        self.steps += 1;
+
+        // 丢掉之后不会再用到的输入
+        let next_expected = (self.steps as f64 * HOP as f64 * self.rate).round() as usize;
+        let keep_from = next_expected.saturating_sub(TOLERANCE).min(start + HOP);
+        while self.input_start < keep_from && self.input_frames() > 0 {
+            self.input.drain(..self.channels);
+            self.input_start += 1;
+        }
+        true
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for TimeStretch<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.output.is_empty() {
+            if self.finished {
+                return None;
+            }
+            if !self.synthesize() {
+                self.finished = true;
+            }
+        }
+        self.output.pop_front()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for TimeStretch<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration().map(|d| d.div_f64(self.rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(freq: f32, secs: f32, rate: u32) -> Vec<f32> {
+        (0..(secs * rate as f32) as usize)
+            .map(|i| (i as f32 / rate as f32 * freq * 2.0 * std::f32::consts::PI).sin())
+            .collect()
+    }
+
+    /// 过零次数 / 2 ≈ 周期数
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn test_stretch_changes_length_keeps_pitch() {
+        let rate = 44100;
+        let input = sine(440.0, 2.0, rate);
+
+        for speed in [0.5, 1.5] {
+            let out: Vec<f32> = TimeStretch::new(SamplesBuffer::new(1, rate, input.clone()), speed).collect();
+            let expected_len = input.len() as f64 / speed;
+            assert!((out.len() as f64 - expected_len).abs() < (FRAME * 2) as f64, "len {} vs {}", out.len(), expected_len);
+
+            // 取中间一秒，频率仍约为 440Hz
+            let mid = &out[out.len() / 2 - rate as usize / 2..out.len() / 2 + rate as usize / 2];
+            let freq = zero_crossings(mid) as f64 / 2.0;
+            assert!((freq - 440.0).abs() < 10.0, "speed {} freq {}", speed, freq);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use mug_tui::schema::{chart_schema, song_schema};
+use std::fs;
+use std::path::PathBuf;
+
+fn main() -> anyhow::Result<()> {
+    // 输出目录，默认当前目录
+    let output_dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".into()));
+    fs::create_dir_all(&output_dir)?;
+
+    for (name, schema) in [("chart.schema.json", chart_schema()), ("song.schema.json", song_schema())] {
+        let path = output_dir.join(name);
+        fs::write(&path, serde_json::to_string_pretty(&schema)?)?;
+        println!("Generated schema: {}", path.display());
+    }
+    Ok(())
+}
@@ -1,4 +1,6 @@
 use mug_tui::convert::mc::convert_mc_to_custom;
+use mug_tui::load::SONG_FORMAT_VERSION;
+use anyhow::Context;
 use std::fs;
 
 fn main() -> anyhow::Result<()> {
@@ -14,10 +16,11 @@ fn main() -> anyhow::Result<()> {
     let mc_content = fs::read_to_string(input_path)?;
 
     // 调用你之前实现的转换函数
-    let (chart, song_meta) = convert_mc_to_custom(&mc_content);
+    let (chart, song_meta) = convert_mc_to_custom(&mc_content)
+        .with_context(|| format!("Failed to convert {}", input_path))?;
 
     // 3. 输出谱面 JSON (charts/at.json)
-    let chart_json = serde_json::to_string_pretty(&chart)?;
+    let chart_json = serde_json::to_string_pretty(&chart.to_value()?)?;
     let chart_output = format!("{}/charts/at.json", output_dir);
     fs::write(&chart_output, chart_json)?;
     println!("Generated chart: {}", chart_output);
@@ -25,6 +28,7 @@ fn main() -> anyhow::Result<()> {
     // 4. 构建并输出 song.json
     // 注意：这里需要补全你 song.json 结构中缺失的路径信息
     let full_song_config = serde_json::json!({
+        "version": SONG_FORMAT_VERSION,
         "meta": song_meta,
         "audio_file": "audio.mp3",          // 默认占位符，需手动改
         "chart_files": ["charts/at.json"], // 对应刚才生成的谱面
@@ -0,0 +1,19 @@
+use mug_tui::convert::sm::convert_sm_song;
+use std::path::PathBuf;
+
+fn main() -> anyhow::Result<()> {
+    // 用法: sm_converter [输入 .sm] [输出目录]
+    let mut args = std::env::args().skip(1);
+    let input = PathBuf::from(args.next().unwrap_or_else(|| "chart.sm".into()));
+    let output_dir = PathBuf::from(args.next().unwrap_or_else(|| "output_song".into()));
+
+    println!("Reading StepMania chart: {}...", input.display());
+    let config = convert_sm_song(&input, &output_dir)?;
+    for chart in &config.chart_files {
+        println!("Generated chart: {}", output_dir.join(chart).display());
+    }
+    println!("Generated config: {}", output_dir.join("song.json").display());
+
+    println!("\nConversion complete! Copy '{}' next to song.json in '{}'.", config.audio_file, output_dir.display());
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::core::bot::BotProfile;
 use crate::core::judge::JudgeCore;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -22,7 +23,17 @@ pub struct PlayingConfig {
     pub show_debug_overlay: bool,
     pub speed: f64,
     pub track_width: u16,
-    pub autoplay: bool
+    pub autoplay: bool,
+    /// autoplay 时的 bot：预设名或自定义抖动/偏差/漏按率
+    #[serde(default)]
+    pub bot: BotProfile,
+    /// 倍速播放时保持音高
+    #[serde(default = "default_preserve_pitch")]
+    pub preserve_pitch: bool,
+}
+
+fn default_preserve_pitch() -> bool {
+    true
 }
 
 impl GlobalConfig {
@@ -31,9 +42,11 @@ impl GlobalConfig {
     }
 }
 pub fn json_to_config(json_str: &str) -> anyhow::Result<GlobalConfig> {
-    let config = serde_json::from_str(json_str).inspect_err(
+    let config: GlobalConfig = serde_json::from_str(json_str).inspect_err(
         |e| error!("Error parsing config: {e}")
     )?;
+    config.playing.judge_core.validate()
+        .inspect_err(|e| error!("Invalid judge config: {e}"))?;
     Ok(config)
 }
 
@@ -41,7 +54,7 @@ pub fn json_to_config(json_str: &str) -> anyhow::Result<GlobalConfig> {
 mod tests {
     use std::collections::HashMap;
     use crate::config::{GlobalConfig, PlayingConfig};
-    use crate::core::judge::{JudgeCore, JudgeWindow};
+    use crate::core::judge::{GhostTapPolicy, HitPolicy, JudgeCore, JudgeWindow, TailMode};
     use crate::core::timing::Time;
     use super::*;
     #[test]
@@ -55,11 +68,15 @@ mod tests {
             ]
         );
         let judge_core = JudgeCore{
-            window: JudgeWindow {
-                perfect: Time(0.08),
-                good: Time(0.16),
-            },
-            hold_tolerance: Time(0.008)
+            window: JudgeWindow::classic(Time(0.08), Time(0.16)),
+            hold_tolerance: Time(0.008),
+            mine_window: Time(0.05),
+            roll_tolerance: Time(0.3),
+            tail_mode: TailMode::Off,
+            tail_window_scale: 1.5,
+            hold_tick: None,
+            hit_policy: HitPolicy::CursorLock,
+            ghost_tap: GhostTapPolicy::Ignore,
         };
         let config: GlobalConfig = GlobalConfig {
             song_dir_path: "./assets".into(),
@@ -75,10 +92,39 @@ mod tests {
                 judge_core,
                 speed: 40.0,
                 track_width: 8,
-                autoplay: false
+                autoplay: false,
+                bot: BotProfile::default(),
+                preserve_pitch: true,
             }
         };
 
         println!("{}", config.to_json().unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reject_invalid_judge_core() {
+        let mut doc: serde_json::Value = serde_json::from_str(include_str!("../config.json")).unwrap();
+        assert!(json_to_config(&doc.to_string()).is_ok());
+        doc["playing"]["judge_core"]["roll_tolerance"] = 0.0.into();
+        assert!(json_to_config(&doc.to_string()).is_err());
+        doc["playing"]["judge_core"]["roll_tolerance"] = 0.3.into();
+        doc["playing"]["judge_core"]["hold_tick"] = 0.25.into();
+        assert!(json_to_config(&doc.to_string()).is_ok());
+        for tiny in [serde_json::json!(0.0), serde_json::json!([0, 1, 1000])] {
+            doc["playing"]["judge_core"]["hold_tick"] = tiny;
+            assert!(json_to_config(&doc.to_string()).is_err());
+        }
+        doc["playing"]["judge_core"]["hold_tick"] = serde_json::Value::Null;
+        for amount in [-0.1, 1.5] {
+            doc["playing"]["judge_core"]["ghost_tap"] = serde_json::json!({"DrainGauge": {"amount": amount}});
+            assert!(json_to_config(&doc.to_string()).is_err(), "{amount}");
+        }
+        doc["playing"]["judge_core"]["ghost_tap"] = serde_json::json!({"DrainGauge": {"amount": 0.05}});
+        assert!(json_to_config(&doc.to_string()).is_ok());
+        for key in ["hold_tolerance", "mine_window", "tail_window_scale"] {
+            let mut bad = doc.clone();
+            bad["playing"]["judge_core"][key] = (-1.0).into();
+            assert!(json_to_config(&bad.to_string()).is_err(), "{key}");
+        }
+    }
+}
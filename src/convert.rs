@@ -1 +1,2 @@
-pub mod mc;
\ No newline at end of file
+pub mod mc;
+pub mod sm;
@@ -1,6 +1,8 @@
+use anyhow::Context;
 use serde::Deserialize;
-use crate::core::chart::{Chart, ChartMeta, Track, Note};
-use crate::core::timing::{Beat, TimingMap, BpmChange, Time};
+use crate::core::chart::{BgmEvent, Chart, ChartMeta, Track, Note};
+use crate::core::difficulty::estimate_difficulty;
+use crate::core::timing::{Beat, TimingMap, BpmChange, Time, ScrollVelocity};
 
 // --- Malody 原始格式定义 ---
 #[derive(Deserialize)]
@@ -31,6 +33,8 @@ struct McModeExtension {
 struct McChart {
     meta: McMeta,
     time: Vec<McTime>,
+    #[serde(default)]
+    effect: Vec<McEffect>,
     note: Vec<McNote>,
 }
 
@@ -41,6 +45,9 @@ struct McNote {
     endbeat: Option<[u32; 3]>,
     sound: Option<String>,
     offset: Option<i32>,
+    // 1 为主音乐，其余无 column 的 sound 项为背景 keysound
+    #[serde(rename = "type", default)]
+    kind: u32,
 }
 
 #[derive(Deserialize)]
@@ -49,19 +56,27 @@ struct McTime {
     bpm: f64,
 }
 
+#[derive(Deserialize)]
+struct McEffect {
+    beat: [u32; 3],
+    scroll: Option<f64>,
+}
+
 // --- 转换逻辑 ---
-fn mc_beat_to_f64(b: [u32; 3]) -> f64 {
-    b[0] as f64 + (b[1] as f64 / b[2] as f64)
+// Malody 的 [拍, 分子, 分母] 直接对应有理数 Beat，不丢精度
+fn mc_beat(b: [u32; 3]) -> anyhow::Result<Beat> {
+    Beat::try_new(b[0] as i64, b[1], b[2]).with_context(|| format!("Bad Malody beat {:?}", b))
 }
 
-pub fn convert_mc_to_custom(mc_json: &str) -> (Chart, crate::models::SongMeta) {
-    let mc: McChart = serde_json::from_str(mc_json).expect("Failed to parse Malody chart");
+pub fn convert_mc_to_custom(mc_json: &str) -> anyhow::Result<(Chart, crate::models::SongMeta)> {
+    let mc: McChart = serde_json::from_str(mc_json).context("Failed to parse Malody chart")?;
 
     // --- 1. 提取全局偏移 (Global Offset) ---
     // Malody 的 offset 单位通常是 ms，我们需要转换为我们的 Time(秒)
     let mut global_offset_ms = 0;
     for n in &mc.note {
-        if n.sound.is_some() {
+        // 带 column 的 sound 是按键音，不是主音乐
+        if n.sound.is_some() && n.column.is_none() && n.kind == 1 {
             if let Some(off) = n.offset {
                 global_offset_ms = off;
                 break; // 通常取第一个遇到的 sound note 的 offset
@@ -70,10 +85,17 @@ pub fn convert_mc_to_custom(mc_json: &str) -> (Chart, crate::models::SongMeta) {
     }
 
     // 2. 转换 TimingMap
-    let bpm_changes = mc.time.iter().map(|t| BpmChange {
-        beat: Beat(mc_beat_to_f64(t.beat)),
+    let bpm_changes = mc.time.iter().map(|t| Ok(BpmChange {
+        beat: mc_beat(t.beat)?,
         bpm: t.bpm,
-    }).collect();
+    })).collect::<anyhow::Result<_>>()?;
+
+    // Malody 的 effect 里带 scroll 的项即变速 (SV)
+    let mut scroll_velocities: Vec<ScrollVelocity> = mc.effect.iter()
+        .filter_map(|e| Some((e.beat, e.scroll?)))
+        .map(|(beat, multiplier)| Ok(ScrollVelocity { beat: mc_beat(beat)?, multiplier }))
+        .collect::<anyhow::Result<_>>()?;
+    scroll_velocities.sort_by_key(|sv| sv.beat);
 
     // 3. 初始化轨道
     let column_count = mc.meta.mode_ext.column as usize;
@@ -81,59 +103,73 @@ pub fn convert_mc_to_custom(mc_json: &str) -> (Chart, crate::models::SongMeta) {
         .map(|i| Track { id: i as u8, notes: vec![] })
         .collect();
 
-    // 4. 转换打击音符 (过滤掉 BGM 项)
-    let mut last_beat: f64 = 0.0;
+    // 4. 转换打击音符，无 column 的 sound 项进入背景音轨
+    let mut last_beat = Beat::ZERO;
+    let mut bgm = Vec::new();
     for n in mc.note {
-        // 如果没有 column，说明是 BGM 项或特殊控制项，跳过
+        // 如果没有 column，说明是 BGM 项或特殊控制项
         let col = match n.column {
             Some(c) => c as usize,
-            None => continue,
+            None => {
+                if let Some(sound) = n.sound
+                    && n.kind != 1
+                {
+                    bgm.push(BgmEvent { beat: mc_beat(n.beat)?, sound });
+                }
+                continue;
+            }
         };
 
         if col >= column_count { continue; }
 
-        let start = mc_beat_to_f64(n.beat);
+        let start = mc_beat(n.beat)?;
         last_beat = last_beat.max(start);
 
         if let Some(eb) = n.endbeat {
-            let end = mc_beat_to_f64(eb);
+            let end = mc_beat(eb)?;
             last_beat = last_beat.max(end);
             tracks[col].notes.push(Note::Hold {
-                start: Beat(start),
-                end: Beat(end)
+                start,
+                end,
+                sound: n.sound,
             });
         } else {
             tracks[col].notes.push(Note::Tap {
-                beat: Beat(start)
+                beat: start,
+                sound: n.sound,
             });
         }
     }
 
     // 对每个轨道排序
     for track in &mut tracks {
-        track.notes.sort_by(|a, b| a.beat().0.partial_cmp(&b.beat().0).unwrap());
+        track.notes.sort_by_key(|n| n.beat());
     }
 
     // 5. 构建 Chart (应用全局 Offset)
-    let chart = Chart {
+    // Malody 的 offset 为负时表示音频比 0 拍更早开始
+    // 我们的系统通常 offset = 秒 (ms / 1000.0)
+    let mut timing_map = TimingMap::new(Time(global_offset_ms as f64 / 1000.0), bpm_changes)
+        .context("Invalid Malody timing")?;
+    timing_map.set_scroll_velocities(scroll_velocities).context("Invalid Malody scroll effects")?;
+
+    let mut chart = Chart {
         meta: ChartMeta {
             charter: mc.meta.creator.clone(),
             level: 0,
             desc: format!("{}K - Converted from Malody", column_count),
         },
-        timing_map: TimingMap {
-            // Malody 的 offset 为负时表示音频比 0 拍更早开始
-            // 我们的系统通常 offset = 秒 (ms / 1000.0)
-            offset: Time(global_offset_ms as f64 / 1000.0),
-            bpm_changes,
-        },
+        timing_map,
         tracks,
+        bgm,
     };
+    // Malody 不带难度等级，用估算值补上
+    chart.meta.level = estimate_difficulty(&chart).round().clamp(0.0, u8::MAX as f64) as u8;
 
     // 6. 歌曲元数据
     let first_bpm = mc.time.get(0).map(|t| t.bpm).unwrap_or(120.0);
     // 粗略计算时长
-    let estimated_secs = (last_beat * 60.0 / first_bpm) + 2.0;
+    let estimated_secs = (last_beat.as_f64() * 60.0 / first_bpm) + 2.0;
 
     let song_meta = crate::models::SongMeta {
         title: mc.meta.song.title.clone(),
@@ -142,7 +178,7 @@ pub fn convert_mc_to_custom(mc_json: &str) -> (Chart, crate::models::SongMeta) {
         bpm: first_bpm,
     };
 
-    (chart, song_meta)
+    Ok((chart, song_meta))
 }
 
 #[cfg(test)]
@@ -162,15 +198,116 @@ mod tests {
             "note": [{"beat": [1, 0, 1], "column": 0}]
         }"#;
 
-        let (chart, meta) = convert_mc_to_custom(raw_mc);
+        let (chart, meta) = convert_mc_to_custom(raw_mc).unwrap();
 
         assert_eq!(chart.tracks.len(), 4);
         assert_eq!(meta.title, "T");
         // 验证第一个音符是否在第 0 轨的 1.0 拍
-        if let Note::Tap { beat } = &chart.tracks[0].notes[0] {
-            assert_eq!(beat.0, 1.0);
+        if let Note::Tap { beat, .. } = &chart.tracks[0].notes[0] {
+            assert_eq!(*beat, Beat::from_int(1));
         } else {
             panic!("Note should be a Tap");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_convert_mc_scroll_effect() {
+        let raw_mc = r#"{
+            "meta": {
+                "$ver": 0, "creator": "test", "background": "b.jpg", "version": "4K",
+                "id": 0, "mode": 0, "time": 0,
+                "song": { "title": "T", "artist": "A", "id": 0, "titleorg": "T", "artistorg": "A" },
+                "mode_ext": { "column": 4, "bar_begin": 0 }
+            },
+            "time": [{"beat": [0, 0, 1], "bpm": 120.0}],
+            "effect": [{"beat": [2, 1, 2], "scroll": 0.5}, {"beat": [1, 0, 1], "jump": 1}],
+            "note": [{"beat": [1, 0, 1], "column": 0}]
+        }"#;
+
+        let (chart, _) = convert_mc_to_custom(raw_mc).unwrap();
+
+        let svs = chart.timing_map.scroll_velocities();
+        assert_eq!(svs.len(), 1);
+        assert_eq!(svs[0].beat, Beat::new(2, 1, 2));
+        assert_eq!(svs[0].multiplier, 0.5);
+    }
+
+    #[test]
+    fn test_convert_mc_keeps_exact_beats() {
+        let raw_mc = r#"{
+            "meta": {
+                "$ver": 0, "creator": "test", "background": "b.jpg", "version": "4K",
+                "id": 0, "mode": 0, "time": 0,
+                "song": { "title": "T", "artist": "A", "id": 0, "titleorg": "T", "artistorg": "A" },
+                "mode_ext": { "column": 4, "bar_begin": 0 }
+            },
+            "time": [{"beat": [0, 0, 1], "bpm": 120.0}],
+            "note": [{"beat": [1, 1, 3], "column": 1}, {"beat": [1, 2, 6], "endbeat": [2, 5, 48], "column": 2}]
+        }"#;
+
+        let (chart, _) = convert_mc_to_custom(raw_mc).unwrap();
+
+        assert_eq!(chart.tracks[1].notes[0].beat(), Beat::new(1, 1, 3));
+        assert_eq!(chart.tracks[1].notes[0].beat().denominator(), 3);
+        assert_eq!(chart.tracks[2].notes[0].end_beat(), Beat::new(2, 5, 48));
+
+        // 经 JSON 往返后仍然精确
+        let json = chart.to_json().unwrap();
+        let back = crate::core::chart::json_to_chart(&json).unwrap();
+        assert_eq!(back.tracks[2].notes[0].end_beat(), Beat::new(2, 5, 48));
+    }
+
+    #[test]
+    fn test_convert_mc_keeps_keysounds() {
+        let raw_mc = r#"{
+            "meta": {
+                "$ver": 0, "creator": "test", "background": "b.jpg", "version": "4K",
+                "id": 0, "mode": 0, "time": 0,
+                "song": { "title": "T", "artist": "A", "id": 0, "titleorg": "T", "artistorg": "A" },
+                "mode_ext": { "column": 4, "bar_begin": 0 }
+            },
+            "time": [{"beat": [0, 0, 1], "bpm": 120.0}],
+            "note": [
+                {"beat": [1, 0, 1], "column": 0, "sound": "kick.wav"},
+                {"beat": [2, 0, 1], "sound": "pad.ogg", "vol": 100},
+                {"beat": [0, 0, 1], "sound": "song.ogg", "vol": 100, "offset": 120, "type": 1}
+            ]
+        }"#;
+
+        let (chart, _) = convert_mc_to_custom(raw_mc).unwrap();
+
+        assert_eq!(chart.tracks[0].notes[0].sound(), Some("kick.wav"));
+        assert_eq!(chart.bgm, vec![BgmEvent { beat: Beat::from_int(2), sound: "pad.ogg".into() }]);
+        assert_eq!(chart.timing_map.offset(), Time(0.12));
+        assert_eq!(chart.keysounds().into_iter().collect::<Vec<_>>(), vec!["kick.wav", "pad.ogg"]);
+
+        // 没有 keysound 的音符不写出 sound 字段
+        let json = chart.to_json().unwrap();
+        assert!(json.contains(r#""sound":"kick.wav""#));
+        assert_eq!(json.matches("\"sound\"").count(), 2);
+        let back = crate::core::chart::json_to_chart(&json).unwrap();
+        assert_eq!(back.bgm, chart.bgm);
+    }
+
+    #[test]
+    fn test_convert_mc_reports_invalid_input() {
+        assert!(convert_mc_to_custom("not json").is_err());
+        let raw_mc = r#"{
+            "meta": {
+                "$ver": 0, "creator": "test", "background": "b.jpg", "version": "4K",
+                "id": 0, "mode": 0, "time": 0,
+                "song": { "title": "T", "artist": "A", "id": 0, "titleorg": "T", "artistorg": "A" },
+                "mode_ext": { "column": 4, "bar_begin": 0 }
+            },
+            "time": [{"beat": [0, 0, 1], "bpm": 0.0}],
+            "note": [{"beat": [1, 0, 1], "column": 0}]
+        }"#;
+        let err = convert_mc_to_custom(raw_mc).unwrap_err();
+        assert!(err.to_string().contains("Invalid Malody timing"), "{err}");
+
+        // 分母为 0 不能悄悄变成别的拍
+        let zero_den = raw_mc.replace(r#""bpm": 0.0"#, r#""bpm": 120.0"#).replace("[1, 0, 1]", "[1, 3, 0]");
+        let err = convert_mc_to_custom(&zero_den).unwrap_err();
+        assert!(format!("{err:#}").contains("denominator"), "{err:#}");
+    }
+}
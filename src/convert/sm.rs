@@ -0,0 +1,273 @@
+use anyhow::{bail, Context};
+use crate::core::chart::{Chart, ChartMeta, Track, Note};
+use crate::core::timing::{Beat, TimingMap, BpmChange, Time, Stop};
+use crate::load::{SongConfig, SONG_FORMAT_VERSION};
+use std::fs;
+use std::path::Path;
+
+// --- StepMania (.sm) 转换 ---
+// .sm 是 `#TAG:VALUE;` 形式的纯文本，一个文件里可以有多个 #NOTES 难度
+
+/// 按 `#TAG:VALUE;` 拆分，去掉 `//` 注释
+fn parse_tags(sm: &str) -> Vec<(String, String)> {
+    let stripped: String = sm
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    stripped
+        .split(';')
+        .filter_map(|chunk| {
+            let chunk = chunk.trim();
+            let body = chunk.strip_prefix('#')?;
+            let (tag, value) = body.split_once(':')?;
+            Some((tag.trim().to_ascii_uppercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// `beat=value,beat=value,...`
+fn parse_pairs(value: &str) -> anyhow::Result<Vec<(Beat, f64)>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|pair| {
+            let (beat, val) = pair.split_once('=').with_context(|| format!("Bad pair '{}'", pair))?;
+            let beat: f64 = beat.trim().parse().with_context(|| format!("Bad beat '{}'", beat))?;
+            let val: f64 = val.trim().parse().with_context(|| format!("Bad value '{}'", val))?;
+            let beat = Beat::try_from_f64(beat).with_context(|| format!("Bad beat '{}'", beat))?;
+            Ok((beat, val))
+        })
+        .collect()
+}
+
+fn parse_notes(value: &str, timing_map: &TimingMap) -> anyhow::Result<Chart> {
+    // 类型:描述/作者:难度名:等级:groove radar:音符数据
+    let fields: Vec<&str> = value.splitn(6, ':').map(str::trim).collect();
+    if fields.len() < 6 {
+        bail!("#NOTES has {} fields, expected 6", fields.len());
+    }
+    let (kind, author, difficulty, meter, data) = (fields[0], fields[1], fields[2], fields[3], fields[5]);
+
+    let measures: Vec<Vec<&str>> = data
+        .split(',')
+        .map(|m| m.lines().map(str::trim).filter(|l| !l.is_empty()).collect())
+        .collect();
+
+    let column_count = measures
+        .iter()
+        .flatten()
+        .map(|row| row.chars().count())
+        .next()
+        .context("#NOTES has no rows")?;
+
+    let mut tracks: Vec<Track> = (0..column_count)
+        .map(|i| {
+            let id = u8::try_from(i).with_context(|| format!("#NOTES has too many columns ({})", column_count))?;
+            Ok(Track { id, notes: vec![] })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    // 每轨尚未闭合的长条头 (起始拍, 是否 roll)
+    let mut hold_heads: Vec<Option<(Beat, bool)>> = vec![None; column_count];
+
+    for (m, rows) in measures.iter().enumerate() {
+        let n = rows.len() as u32;
+        for (i, row) in rows.iter().enumerate() {
+            // 第 m 小节第 i 行 (共 n 行)，每小节固定 4 拍
+            let beat = Beat::new(m as i64 * 4, i as u32 * 4, n);
+            for (col, c) in row.chars().enumerate().take(column_count) {
+                match c {
+                    '1' => tracks[col].notes.push(Note::Tap { beat, sound: None }),
+                    '2' => hold_heads[col] = Some((beat, false)),
+                    '4' => hold_heads[col] = Some((beat, true)),
+                    '3' => match hold_heads[col].take() {
+                        Some((start, false)) => tracks[col].notes.push(Note::Hold { start, end: beat, sound: None }),
+                        Some((start, true)) => tracks[col].notes.push(Note::Roll { start, end: beat, sound: None }),
+                        None => log::warn!("Hold tail without head at beat {} col {}", beat, col),
+                    },
+                    'M' => tracks[col].notes.push(Note::Mine { beat }),
+                    // 0 为空，其余 (F/L/K 等) 不支持
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for track in &mut tracks {
+        track.notes.sort_by_key(|n| n.beat());
+    }
+
+    let chart = Chart {
+        meta: ChartMeta {
+            charter: author.to_string(),
+            level: meter.parse::<f64>().unwrap_or(0.0).round().clamp(0.0, u8::MAX as f64) as u8,
+            desc: format!("{} {} - Converted from StepMania", kind, difficulty),
+        },
+        timing_map: timing_map.clone(),
+        tracks,
+        bgm: vec![],
+    };
+    Ok(chart)
+}
+
+pub fn convert_sm_to_custom(sm: &str) -> anyhow::Result<(Vec<Chart>, crate::models::SongMeta)> {
+    let tags = parse_tags(sm);
+    let get = |name: &str| tags.iter().find(|(t, _)| t == name).map(|(_, v)| v.as_str());
+
+    // 1. TimingMap
+    // .sm 的 #OFFSET 是 0 拍相对音频开头的时间取负
+    let offset: f64 = get("OFFSET").unwrap_or("0").parse().context("Bad #OFFSET")?;
+    let bpm_changes: Vec<BpmChange> = parse_pairs(get("BPMS").context("Missing #BPMS")?)?
+        .into_iter()
+        .map(|(beat, bpm)| BpmChange { beat, bpm })
+        .collect();
+    let first_bpm = bpm_changes.first().map(|b| b.bpm).unwrap_or(120.0);
+
+    let mut timing_map = TimingMap::new(Time(-offset), bpm_changes)?;
+    timing_map.set_stops(parse_pairs(get("STOPS").unwrap_or(""))?
+        .into_iter()
+        .filter(|(_, secs)| *secs > 0.0)
+        .map(|(beat, secs)| Stop { beat, duration: Time(secs) })
+        .collect())?;
+
+    // 2. 每个 #NOTES 一张谱
+    let charts = tags
+        .iter()
+        .filter(|(t, _)| t == "NOTES")
+        .map(|(_, v)| parse_notes(v, &timing_map))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if charts.is_empty() {
+        bail!("No #NOTES in StepMania file");
+    }
+
+    // 3. 歌曲元数据
+    let last_beat = charts
+        .iter()
+        .flat_map(|c| c.tracks.iter().flat_map(|t| t.notes.iter().map(|n| n.end_beat())))
+        .max()
+        .unwrap_or(Beat::ZERO);
+    let estimated_secs = timing_map.beat_to_time(&last_beat).0 + 2.0;
+
+    let song_meta = crate::models::SongMeta {
+        title: get("TITLE").unwrap_or("").to_string(),
+        artist: get("ARTIST").unwrap_or("").to_string(),
+        length: std::time::Duration::from_secs(estimated_secs.max(0.0) as u64),
+        bpm: first_bpm,
+    };
+
+    Ok((charts, song_meta))
+}
+
+/// Convert the .sm file at `input` into a song directory `output_dir` that
+/// `load::load_all_songs` can read: one json chart per #NOTES and a song.json
+/// pointing at the audio and background named by the .sm
+pub fn convert_sm_song(input: &Path, output_dir: &Path) -> anyhow::Result<SongConfig> {
+    let sm = fs::read_to_string(input).with_context(|| format!("Failed to read {input:?}"))?;
+    let (charts, meta) = convert_sm_to_custom(&sm).with_context(|| format!("Failed to convert {input:?}"))?;
+    let tags = parse_tags(&sm);
+    let get = |name: &str| tags.iter().find(|(t, v)| t == name && !v.is_empty()).map(|(_, v)| v.clone());
+
+    fs::create_dir_all(output_dir.join("charts"))?;
+    let mut chart_files = vec![];
+    for (i, chart) in charts.iter().enumerate() {
+        let file = format!("charts/{i}.json");
+        fs::write(output_dir.join(&file), serde_json::to_string_pretty(&chart.to_value()?)?)?;
+        chart_files.push(file);
+    }
+
+    let config = SongConfig {
+        version: SONG_FORMAT_VERSION,
+        meta,
+        // 音频和背景图需要和 .sm 放在一起复制过去
+        audio_file: get("MUSIC").unwrap_or_else(|| "audio.ogg".into()),
+        chart_files,
+        illu_file: get("BACKGROUND"),
+    };
+    fs::write(output_dir.join("song.json"), serde_json::to_string_pretty(&config)?)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_sm_to_custom() {
+        let raw_sm = r#"
+#TITLE:T;
+#ARTIST:A;
+#OFFSET:-0.5;
+#BPMS:0.000=120.000;
+#STOPS:4.000=1.000;
+#NOTES:
+     dance-single:
+     someone:
+     Hard:
+     7:
+     0,0,0,0,0:
+1000
+0M00
+2000
+3001
+,  // measure 1
+0000
+00M0
+4000
+3000
+;
+"#;
+        let (charts, meta) = convert_sm_to_custom(raw_sm).unwrap();
+
+        assert_eq!(meta.title, "T");
+        assert_eq!(charts.len(), 1);
+        let chart = &charts[0];
+        assert_eq!(chart.tracks.len(), 4);
+        assert_eq!(chart.meta.level, 7);
+        assert_eq!(chart.timing_map.offset(), Time(0.5));
+        assert_eq!(chart.timing_map.stops().len(), 1);
+
+        assert_eq!(chart.tracks[0].notes, vec![
+            Note::Tap { beat: Beat::from_int(0), sound: None },
+            Note::Hold { start: Beat::from_int(2), end: Beat::from_int(3), sound: None },
+            Note::Roll { start: Beat::from_int(6), end: Beat::from_int(7), sound: None },
+        ]);
+        assert_eq!(chart.tracks[1].notes, vec![Note::Mine { beat: Beat::from_int(1) }]);
+        assert_eq!(chart.tracks[2].notes, vec![Note::Mine { beat: Beat::from_int(5) }]);
+        assert_eq!(chart.tracks[3].notes, vec![Note::Tap { beat: Beat::from_int(3), sound: None }]);
+    }
+
+    #[test]
+    fn test_convert_sm_rejects_bad_input() {
+        let notes = "#NOTES:dance-single:a:Easy:2:0:\n1000\n;";
+        assert!(convert_sm_to_custom(&format!("#BPMS:nan=120;\n{notes}")).is_err());
+        assert!(convert_sm_to_custom(&format!("#BPMS:0=120;\n#STOPS:1e300=0.5;\n{notes}")).is_err());
+
+        let wide = format!("#BPMS:0=120;\n#NOTES:dance-single:a:Easy:2:0:\n{}\n;", "0".repeat(300));
+        let err = convert_sm_to_custom(&wide).unwrap_err();
+        assert!(format!("{err:#}").contains("too many columns"), "{err:#}");
+    }
+
+    #[test]
+    fn test_convert_sm_song_loads() {
+        let root = std::env::temp_dir().join(format!("mug-tui-sm-{}", std::process::id()));
+        let input = root.join("in.sm");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&input, "#TITLE:T;\n#MUSIC:song.ogg;\n#BPMS:0=120;\n\
+            #NOTES:dance-single:a:Easy:2:0:\n1000\n0100\n0010\n0001\n;\n\
+            #NOTES:dance-single:a:Hard:9:0:\n2000\n0000\n3000\n1111\n;").unwrap();
+
+        let config = convert_sm_song(&input, &root.join("songs/t")).unwrap();
+        let songs = crate::load::load_all_songs(root.join("songs"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!((config.audio_file.as_str(), config.chart_files.len()), ("song.ogg", 2));
+        let songs = songs.unwrap();
+        assert_eq!(songs.len(), 1);
+        assert_eq!(songs[0].meta.title, "T");
+        let levels: Vec<u8> = songs[0].charts.iter().map(|c| c.meta.level).collect();
+        assert_eq!(levels, vec![2, 9]);
+        assert_eq!(songs[0].charts[1].tracks[0].notes[0], Note::Hold { start: Beat::ZERO, end: Beat::from_int(2), sound: None });
+    }
+}
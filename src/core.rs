@@ -1,3 +1,9 @@
+pub mod analysis;
+pub mod bot;
 pub mod chart;
+pub mod difficulty;
 pub mod judge;
+pub mod modifier;
+mod rng;
+pub mod session;
 pub mod timing;
\ No newline at end of file
@@ -0,0 +1,153 @@
+//! Chart statistics derived from note placement and timing.
+//! Holds and rolls count as one note, judged on their head. Mines are only counted.
+
+use crate::core::chart::{Chart, Note};
+use crate::core::timing::{Beat, Time};
+use std::collections::BTreeMap;
+
+/// Width of the sliding window used for notes-per-second
+pub const NPS_WINDOW: Time = Time(1.0);
+/// Distance between two samples of [`ChartAnalysis::nps`]
+pub const NPS_STEP: Time = Time(0.5);
+
+#[derive(Debug, Clone, Default)]
+pub struct ChartAnalysis {
+    pub total_notes: usize,
+    pub tap_count: usize,
+    pub hold_count: usize,
+    pub roll_count: usize,
+    pub mine_count: usize,
+    /// From the first note head to the end of the last note
+    pub drain_time: Time,
+    /// Notes per second in a [`NPS_WINDOW`] centred on every [`NPS_STEP`]
+    pub nps: Vec<f64>,
+    pub peak_nps: f64,
+    /// Chord size -> how many times notes land together in that many lanes
+    pub chords: BTreeMap<usize, usize>,
+    /// (track id, note count) in chart order
+    pub lane_load: Vec<(u8, usize)>,
+}
+
+impl ChartAnalysis {
+    pub fn average_nps(&self) -> f64 {
+        if self.drain_time.0 <= 0.0 {
+            return self.total_notes as f64;
+        }
+        self.total_notes as f64 / self.drain_time.0
+    }
+}
+
+pub fn analyze(chart: &Chart) -> ChartAnalysis {
+    let map = &chart.timing_map;
+    let mut result = ChartAnalysis::default();
+
+    let mut heads: Vec<Time> = Vec::new();
+    let mut chord_beats: BTreeMap<Beat, usize> = BTreeMap::new();
+    let mut last_end: Option<Time> = None;
+
+    for track in &chart.tracks {
+        result.lane_load.push((track.id, track.notes.iter().filter(|n| !n.is_mine()).count()));
+        for note in &track.notes {
+            match note {
+                Note::Tap { .. } => result.tap_count += 1,
+                Note::Hold { .. } => result.hold_count += 1,
+                Note::Roll { .. } => result.roll_count += 1,
+                Note::Mine { .. } => {
+                    result.mine_count += 1;
+                    continue;
+                }
+            }
+            heads.push(map.beat_to_time(&note.beat()));
+            *chord_beats.entry(note.beat()).or_default() += 1;
+
+            let end = map.beat_to_time(&note.end_beat());
+            last_end = Some(last_end.map_or(end, |t: Time| if end > t { end } else { t }));
+        }
+    }
+    result.total_notes = heads.len();
+    if heads.is_empty() {
+        return result;
+    }
+
+    heads.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let first = heads[0];
+    result.drain_time = last_end.unwrap_or(first) - first;
+
+    for size in chord_beats.into_values() {
+        *result.chords.entry(size).or_default() += 1;
+    }
+
+    // 峰值：以每个音符为窗口起点，双指针数窗口内音符
+    let mut hi = 0;
+    let mut peak = 0;
+    for lo in 0..heads.len() {
+        while hi < heads.len() && heads[hi].0 < heads[lo].0 + NPS_WINDOW.0 {
+            hi += 1;
+        }
+        peak = peak.max(hi - lo);
+    }
+    result.peak_nps = peak as f64 / NPS_WINDOW.0;
+
+    let last_head = heads[heads.len() - 1];
+    let samples = ((last_head - first).0 / NPS_STEP.0).floor() as usize + 1;
+    result.nps = (0..samples)
+        .map(|i| {
+            let center = first.0 + i as f64 * NPS_STEP.0;
+            let from = heads.partition_point(|t| t.0 < center - NPS_WINDOW.0 / 2.0);
+            let to = heads.partition_point(|t| t.0 < center + NPS_WINDOW.0 / 2.0);
+            (to - from) as f64 / NPS_WINDOW.0
+        })
+        .collect();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{ChartMeta, Track};
+    use crate::core::timing::{BpmChange, TimingMap};
+
+    #[test]
+    fn test_analyze_chart() {
+        // 120 BPM，一拍 0.5s
+        let map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 120.0 }]).unwrap();
+        let tracks = vec![
+            Track { id: 0, notes: vec![
+                Note::Tap { beat: Beat::from_int(0), sound: None },
+                Note::Tap { beat: Beat::new(0, 1, 2), sound: None },
+                Note::Tap { beat: Beat::from_int(1), sound: None },
+            ] },
+            Track { id: 1, notes: vec![
+                Note::Tap { beat: Beat::from_int(1), sound: None },
+                Note::Hold { start: Beat::from_int(4), end: Beat::from_int(8), sound: None },
+            ] },
+            Track { id: 2, notes: vec![
+                Note::Tap { beat: Beat::from_int(1), sound: None },
+                Note::Mine { beat: Beat::from_int(2) },
+            ] },
+        ];
+        let chart = Chart {
+            meta: ChartMeta { charter: "t".into(), level: 1, desc: String::new() },
+            timing_map: map,
+            tracks,
+            bgm: vec![],
+        };
+
+        let a = analyze(&chart);
+        assert_eq!(a.total_notes, 6);
+        assert_eq!(a.tap_count, 5);
+        assert_eq!(a.hold_count, 1);
+        assert_eq!(a.mine_count, 1);
+        assert!((a.drain_time.0 - 4.0).abs() < 1e-9);
+        // 0.0, 0.25, 0.5 x3 都在同一秒内
+        assert!((a.peak_nps - 5.0).abs() < 1e-9);
+        assert_eq!(a.chords.get(&1), Some(&3));
+        assert_eq!(a.chords.get(&3), Some(&1));
+        assert_eq!(a.lane_load, vec![(0, 3), (1, 2), (2, 1)]);
+        // 头 0s ~ 2s，每 0.5s 一个采样
+        assert_eq!(a.nps.len(), 5);
+        assert!((a.nps[0] - 2.0).abs() < 1e-9);
+        assert!((a.average_nps() - 1.5).abs() < 1e-9);
+    }
+}
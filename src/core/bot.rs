@@ -0,0 +1,264 @@
+//! 自动游玩：按 [`BotProfile`] 把谱面变成按键序列，和键盘走同一条判定路径
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use crate::core::chart::Note;
+use crate::core::judge::JudgeManager;
+use crate::core::rng::SplitMix64;
+use crate::core::session::{KeyInput, Scripted};
+use crate::core::timing::Time;
+
+/// Tap 按下后多久松开
+const TAP_LENGTH: f64 = 0.05;
+/// Roll 连打的最短间隔，防止容错设得极小时生成无数按键
+const MIN_ROLL_INTERVAL: f64 = 0.01;
+
+/// How a bot presses keys. Offsets are `bias + jitter × N(0, 1)` seconds,
+/// drawn independently for every press and release. Written in config as a
+/// preset name or as `{"jitter": .., "bias": .., "miss_rate": .., "seed": ..}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BotProfileRepr", into = "BotProfileRepr")]
+pub struct BotProfile {
+    /// Standard deviation of the timing error
+    pub jitter: Time,
+    /// Mean timing error, positive is late
+    pub bias: Time,
+    /// Chance in 0..=1 to skip a note entirely
+    pub miss_rate: f64,
+    /// Same seed, same chart → same inputs
+    pub seed: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BotProfileRepr {
+    Preset(BotPreset),
+    Custom {
+        #[serde(default)]
+        jitter: Time,
+        #[serde(default)]
+        bias: Time,
+        #[serde(default)]
+        miss_rate: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// Typical players
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BotPreset {
+    /// Every note exactly on time, the old autoplay
+    Perfect,
+    Average,
+    Beginner,
+}
+
+impl From<BotPreset> for BotProfile {
+    fn from(preset: BotPreset) -> Self {
+        let (jitter, bias, miss_rate) = match preset {
+            BotPreset::Perfect => (0.0, 0.0, 0.0),
+            BotPreset::Average => (0.025, 0.005, 0.02),
+            BotPreset::Beginner => (0.05, 0.015, 0.08),
+        };
+        Self { jitter: Time(jitter), bias: Time(bias), miss_rate, seed: 0 }
+    }
+}
+
+impl Default for BotProfile {
+    fn default() -> Self {
+        BotPreset::Perfect.into()
+    }
+}
+
+impl TryFrom<BotProfileRepr> for BotProfile {
+    type Error = String;
+
+    fn try_from(repr: BotProfileRepr) -> Result<Self, Self::Error> {
+        match repr {
+            BotProfileRepr::Preset(preset) => Ok(preset.into()),
+            BotProfileRepr::Custom { jitter, bias, miss_rate, seed } => {
+                Self::new(jitter, bias, miss_rate, seed).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+impl From<BotProfile> for BotProfileRepr {
+    fn from(p: BotProfile) -> Self {
+        BotProfileRepr::Custom { jitter: p.jitter, bias: p.bias, miss_rate: p.miss_rate, seed: p.seed }
+    }
+}
+
+impl BotProfile {
+    pub fn new(jitter: Time, bias: Time, miss_rate: f64, seed: u64) -> anyhow::Result<Self> {
+        if !(jitter.0.is_finite() && jitter.0 >= 0.0) {
+            bail!("Bot jitter {} must be a non-negative number of seconds", jitter.0);
+        }
+        if !bias.0.is_finite() {
+            bail!("Bot bias {} is not finite", bias.0);
+        }
+        if !(0.0..=1.0).contains(&miss_rate) {
+            bail!("Bot miss rate {} is not within 0..=1", miss_rate);
+        }
+        Ok(Self { jitter, bias, miss_rate, seed })
+    }
+
+    /// 为 `manager` 里的谱面（已按倍速缩放）生成全部按键
+    pub fn play(&self, manager: &JudgeManager) -> Scripted {
+        let map = &manager.map;
+        let mut rng = SplitMix64::new(self.seed);
+        let mut inputs = vec![];
+        for judge in &manager.judges {
+            // 每次按下的 (按下, 松开)
+            let mut presses: Vec<(f64, f64)> = vec![];
+            for note in &judge.notes {
+                if note.is_mine() || rng.chance(self.miss_rate) {
+                    continue;
+                }
+                let start = map.beat_to_time(&note.beat()).0 + self.offset(&mut rng);
+                match note {
+                    Note::Tap { .. } => presses.push((start, start + TAP_LENGTH)),
+                    Note::Hold { end, .. } => {
+                        let end = map.beat_to_time(end).0 + self.offset(&mut rng);
+                        presses.push((start, end.max(start)));
+                    }
+                    Note::Roll { end, .. } => {
+                        // 每过半个容错间隔敲一次
+                        let interval = (manager.core.roll_tolerance.0 / 2.0).max(MIN_ROLL_INTERVAL);
+                        let end = map.beat_to_time(end).0;
+                        let mut t = start;
+                        loop {
+                            presses.push((t, t + TAP_LENGTH.min(interval / 2.0)));
+                            t += interval;
+                            if t >= end {
+                                break;
+                            }
+                        }
+                    }
+                    Note::Mine { .. } => {}
+                }
+            }
+            // 抖动可能打乱顺序；同一轨道上按下和松开必须交替
+            presses.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (i, &(down, up)) in presses.iter().enumerate() {
+                let up = match presses.get(i + 1) {
+                    Some(&(next, _)) => up.min(next).max(down),
+                    None => up,
+                };
+                inputs.push(KeyInput { time: Time(down), track: judge.id, is_down: true });
+                inputs.push(KeyInput { time: Time(up), track: judge.id, is_down: false });
+            }
+        }
+        Scripted::new(inputs)
+    }
+
+    fn offset(&self, rng: &mut SplitMix64) -> f64 {
+        self.bias.0 + self.jitter.0 * rng.normal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{Chart, ChartMeta, Track};
+    use crate::core::judge::{JudgeCore, JudgeResult, JudgeWindow, TailMode};
+    use crate::core::session::{GameSession, InputSource};
+    use crate::core::timing::{Beat, BpmChange, TimingMap};
+
+    const FRAME: Time = Time(1.0 / 240.0);
+
+    /// 120 BPM 的四轨谱面：纵连、Hold、Roll 和地雷
+    fn setup() -> (Chart, JudgeCore) {
+        let taps = (0..16).map(|i| Note::Tap { beat: Beat::new(2 + i / 2, (i % 2) as u32, 2), sound: None }).collect();
+        let chart = Chart {
+            meta: ChartMeta { charter: "t".into(), level: 0, desc: String::new() },
+            timing_map: TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 120.0 }]).unwrap(),
+            tracks: vec![
+                Track { id: 0, notes: taps },
+                Track { id: 1, notes: vec![
+                    Note::Hold { start: Beat::from(2.0), end: Beat::from(4.0), sound: None },
+                    Note::Tap { beat: Beat::from(4.5), sound: None },
+                ] },
+                Track { id: 2, notes: vec![Note::Roll { start: Beat::from(3.0), end: Beat::from(6.0), sound: None }] },
+                Track { id: 3, notes: vec![Note::Mine { beat: Beat::from(3.0) }, Note::Tap { beat: Beat::from(5.0), sound: None }] },
+            ],
+            bgm: vec![],
+        };
+        let mut core = JudgeCore::new(JudgeWindow::classic(Time(0.03), Time(0.08)), Time(0.008));
+        core.tail_mode = TailMode::Combined;
+        (chart, core)
+    }
+
+    fn run(profile: &BotProfile) -> (GameSession, Scripted) {
+        let (chart, core) = setup();
+        let session = GameSession::new(&chart, core, 1.0).unwrap();
+        let bot = profile.play(&session.manager);
+        (session, bot)
+    }
+
+    #[test]
+    fn test_perfect_bot_full_combo() {
+        let (session, mut bot) = run(&BotPreset::Perfect.into());
+        let result = session.simulate(&mut bot, FRAME).unwrap();
+        assert!((result.accuracy - 101.0).abs() < 1e-9, "{}", result.accuracy);
+        assert!(result.events.iter().all(|e| matches!(e.result, JudgeResult::Hit { tier: 0, .. })));
+        assert_eq!(result.max_combo, 20);
+    }
+
+    #[test]
+    fn test_humanized_bots() {
+        let average = BotProfile { seed: 7, ..BotPreset::Average.into() };
+        let first = run(&average).0.simulate(&mut run(&average).1, FRAME).unwrap();
+        let second = run(&average).0.simulate(&mut run(&average).1, FRAME).unwrap();
+        assert_eq!(first.events, second.events);
+        assert!(first.accuracy < 101.0 && first.accuracy > 50.0, "{}", first.accuracy);
+
+        let beginner = BotProfile { seed: 7, ..BotPreset::Beginner.into() };
+        let worse = run(&beginner).0.simulate(&mut run(&beginner).1, FRAME).unwrap();
+        assert!(worse.accuracy < first.accuracy);
+
+        // 全部放弃时不按任何键
+        let afk = BotProfile::new(Time(0.0), Time(0.0), 1.0, 0).unwrap();
+        let (session, mut bot) = run(&afk);
+        assert!(bot.inputs().is_empty());
+        assert_eq!(session.simulate(&mut bot, FRAME).unwrap().score, 0);
+    }
+
+    #[test]
+    fn test_bot_inputs_alternate_per_track() {
+        // 抖动远大于音符间隔
+        let wild = BotProfile::new(Time(0.3), Time(0.0), 0.0, 1).unwrap();
+        let mut bot = run(&wild).1;
+        let inputs = bot.poll(Time(100.0));
+        assert!(bot.poll(Time(100.0)).is_empty());
+        assert!(inputs.windows(2).all(|w| w[0].time <= w[1].time));
+        for track in 0..3 {
+            let downs: Vec<bool> = inputs.iter().filter(|i| i.track == track).map(|i| i.is_down).collect();
+            assert!(!downs.is_empty());
+            assert!(downs.chunks(2).all(|c| c == [true, false]), "track {track}");
+        }
+    }
+
+    #[test]
+    fn test_roll_with_zero_tolerance_terminates() {
+        let (chart, mut core) = setup();
+        core.roll_tolerance = Time(0.0);
+        let session = GameSession::new(&chart, core, 1.0).unwrap();
+        let bot = BotProfile::default().play(&session.manager);
+        // Roll 1.5s~3s，按最短间隔敲击（浮点累加可能多一次）
+        let roll_presses = bot.inputs().iter().filter(|i| i.track == 2 && i.is_down).count();
+        assert!(roll_presses.abs_diff((1.5 / MIN_ROLL_INTERVAL) as usize) <= 1, "{roll_presses}");
+    }
+
+    #[test]
+    fn test_bot_profile_from_config() {
+        let preset: BotProfile = serde_json::from_str(r#""average""#).unwrap();
+        assert_eq!(preset, BotPreset::Average.into());
+        let custom: BotProfile = serde_json::from_str(r#"{"jitter": 0.01, "miss_rate": 0.5}"#).unwrap();
+        assert_eq!(custom, BotProfile { jitter: Time(0.01), bias: Time(0.0), miss_rate: 0.5, seed: 0 });
+        assert!(serde_json::from_str::<BotProfile>(r#"{"jitter": -0.01}"#).is_err());
+        assert!(serde_json::from_str::<BotProfile>(r#"{"miss_rate": 1.5}"#).is_err());
+    }
+}
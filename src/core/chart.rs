@@ -1,14 +1,32 @@
 use anyhow::bail;
-use log::error;
+use log::{error, warn};
 use crate::core::timing::{Beat, TimingMap};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+/// Written as the `version` field of every chart file, see `load::migrate`
+pub const CHART_FORMAT_VERSION: u32 = 1;
+
+mod text;
+pub use text::{chart_to_text, text_to_chart, TEXT_CHART_EXTENSION};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
 pub struct Chart {
     pub meta: ChartMeta,
     pub timing_map: TimingMap,
-    pub tracks: Vec<Track>
+    pub tracks: Vec<Track>,
+    /// 背景音轨：到时间自动播放的 keysound，不参与判定
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bgm: Vec<BgmEvent>,
+}
+
+/// A sample triggered at `beat` regardless of input
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BgmEvent {
+    pub beat: Beat,
+    /// Sample file, relative to the song directory
+    pub sound: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -23,33 +41,287 @@ pub struct ChartMeta {
     pub level: u8,
     pub desc: String,
 }
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Note {
-    Tap { beat: Beat },
-    Hold { start: Beat, end: Beat },
+    Tap {
+        beat: Beat,
+        /// Keysound played on hit, relative to the song directory
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sound: Option<String>,
+    },
+    Hold {
+        start: Beat,
+        end: Beat,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sound: Option<String>,
+    },
+    /// Like a hold, but re-tapped instead of held, see `JudgeCore::roll_tolerance`
+    Roll {
+        start: Beat,
+        end: Beat,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sound: Option<String>,
+    },
+    /// Must not be pressed, see `JudgeCore::mine_window`
+    Mine { beat: Beat },
+}
+
+/// [`Note`] without its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKind {
+    Tap,
+    Hold,
+    Roll,
+    Mine,
 }
 
 impl Note {
     pub fn beat(&self) -> Beat {
         match self{
-            Note::Tap{beat} => beat.clone(),
-            Note::Hold {start, ..}=>start.clone()
+            Note::Tap{beat, .. } => *beat,
+            Note::Hold {start, ..} | Note::Roll { start, .. } => *start,
+            Note::Mine { beat } => *beat,
+        }
+    }
+
+    /// Keysound of the note, if any
+    pub fn sound(&self) -> Option<&str> {
+        match self {
+            Note::Tap { sound, .. } | Note::Hold { sound, .. } | Note::Roll { sound, .. } => sound.as_deref(),
+            Note::Mine { .. } => None,
+        }
+    }
+
+    pub fn is_mine(&self) -> bool {
+        matches!(self, Note::Mine { .. })
+    }
+
+    pub fn kind(&self) -> NoteKind {
+        match self {
+            Note::Tap { .. } => NoteKind::Tap,
+            Note::Hold { .. } => NoteKind::Hold,
+            Note::Roll { .. } => NoteKind::Roll,
+            Note::Mine { .. } => NoteKind::Mine,
+        }
+    }
+
+    /// Last beat the note occupies its lane
+    pub fn end_beat(&self) -> Beat {
+        match self {
+            Note::Tap { beat, .. } | Note::Mine { beat } => *beat,
+            Note::Hold { end, .. } | Note::Roll { end, .. } => *end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The chart cannot be played, loading refuses it
+    Error,
+    /// The chart is repaired by [`Chart::auto_fix`] when loaded
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChartDiagnostic {
+    pub severity: Severity,
+    pub track: Option<u8>,
+    pub note_idx: Option<usize>, // index in the track as written in the file
+    pub message: String,
+}
+
+impl ChartDiagnostic {
+    fn chart(severity: Severity, message: String) -> Self {
+        Self { severity, track: None, note_idx: None, message }
+    }
+
+    fn track(severity: Severity, track: u8, message: String) -> Self {
+        Self { severity, track: Some(track), note_idx: None, message }
+    }
+
+    fn note(severity: Severity, track: u8, note_idx: usize, message: String) -> Self {
+        Self { severity, track: Some(track), note_idx: Some(note_idx), message }
+    }
+}
+
+impl fmt::Display for ChartDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:?}]", self.severity)?;
+        if let Some(track) = self.track {
+            write!(f, " track {track}")?;
+        }
+        if let Some(idx) = self.note_idx {
+            write!(f, " note #{idx}")?;
         }
+        write!(f, ": {}", self.message)
     }
 }
+
 impl Chart {
+    /// Every distinct sample referenced by notes and the bgm lane
+    pub fn keysounds(&self) -> BTreeSet<&str> {
+        self.tracks.iter()
+            .flat_map(|t| t.notes.iter().filter_map(Note::sound))
+            .chain(self.bgm.iter().map(|e| e.sound.as_str()))
+            .collect()
+    }
+
     pub fn to_json(&self) -> serde_json::Result<String> {
-        serde_json::to_string(&self)
+        serde_json::to_string(&self.to_value()?)
+    }
+
+    /// The chart document with its format version, as written to chart files
+    pub fn to_value(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("version".into(), CHART_FORMAT_VERSION.into());
+        }
+        Ok(value)
+    }
+
+    /// Check everything the judge and the timing map rely on.
+    /// Nothing is modified, see [`Chart::auto_fix`] for the warnings.
+    pub fn validate(&self) -> Vec<ChartDiagnostic> {
+        use Severity::*;
+        let mut diags = Vec::new();
+        let map = &self.timing_map;
+
+        // 数值检查和 TimingMap::build 共用，排序问题由 auto_fix 修复
+        if let Err(e) = map.validate() {
+            diags.push(ChartDiagnostic::chart(Error, e.to_string()));
+        }
+        for what in map.unsorted() {
+            diags.push(ChartDiagnostic::chart(Warning, format!("The {what} are not sorted by beat")));
+        }
+
+        let mut seen_ids = HashSet::new();
+        for track in &self.tracks {
+            if !seen_ids.insert(track.id) {
+                diags.push(ChartDiagnostic::track(Error, track.id, format!("Duplicate track id {}", track.id)));
+            }
+
+            for (i, note) in track.notes.iter().enumerate() {
+                let (kind, start, end) = match note {
+                    Note::Hold { start, end, .. } => ("Hold", start, end),
+                    Note::Roll { start, end, .. } => ("Roll", start, end),
+                    _ => continue,
+                };
+                if end < start {
+                    diags.push(ChartDiagnostic::note(Error, track.id, i, format!("{} ends at beat {} before its start {}", kind, end, start)));
+                } else if end == start {
+                    diags.push(ChartDiagnostic::note(Warning, track.id, i, format!("Zero-length {} at beat {}, treated as a tap", kind.to_lowercase(), start)));
+                }
+            }
+
+            if !track.notes.iter().map(|n| n.beat()).is_sorted() {
+                diags.push(ChartDiagnostic::track(Warning, track.id, "Notes are not sorted by beat".into()));
+            }
+
+            for (prev, i) in overlapping_notes(&track.notes) {
+                diags.push(ChartDiagnostic::note(
+                    Warning,
+                    track.id,
+                    i,
+                    format!("Note at beat {} overlaps note #{prev}, dropped", track.notes[i].beat()),
+                ));
+            }
+        }
+
+        diags
+    }
+
+    /// Repair every [`Severity::Warning`] reported by [`Chart::validate`]:
+    /// sort timing events and notes, turn zero-length holds/rolls into taps and
+    /// drop notes that overlap an earlier one in the same track.
+    /// Errors are left untouched.
+    pub fn auto_fix(&mut self) {
+        // 有 Error 时 build 失败，map 保持未构建，由调用方处理
+        let _ = self.timing_map.sort_events();
+
+        for track in &mut self.tracks {
+            for note in &mut track.notes {
+                if let Note::Hold { start, end, sound } | Note::Roll { start, end, sound } = note
+                    && start == end
+                {
+                    *note = Note::Tap { beat: *start, sound: sound.take() };
+                }
+            }
+            let dropped: HashSet<usize> = overlapping_notes(&track.notes).into_iter().map(|(_, i)| i).collect();
+            let notes = std::mem::take(&mut track.notes);
+            track.notes = notes
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| !dropped.contains(i))
+                .map(|(_, n)| n)
+                .collect();
+            track.notes.sort_by_key(|n| n.beat());
+        }
     }
 }
+
+/// (kept, dropped) index pairs of notes that start on or inside an earlier
+/// note of the same track, in beat order
+fn overlapping_notes(notes: &[Note]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..notes.len()).collect();
+    order.sort_by_key(|&i| notes[i].beat());
+
+    let mut result = Vec::new();
+    let mut last: Option<usize> = None;
+    for i in order {
+        if let Some(prev) = last {
+            let (prev_start, prev_end) = (notes[prev].beat(), notes[prev].end_beat());
+            let start = notes[i].beat();
+            if start == prev_start || start < prev_end {
+                result.push((prev, i));
+                continue;
+            }
+        }
+        last = Some(i);
+    }
+    result
+}
+
+/// Parse a chart, refusing it on any [`Severity::Error`] and auto-fixing warnings
 pub fn json_to_chart(json_str: &str) -> anyhow::Result<Chart> {
-    let chart: Chart = serde_json::from_str(json_str)
+    let value: serde_json::Value = serde_json::from_str(json_str)
+        .inspect_err(|e| error!("Error parsing json to chart: {e}"))?;
+    value_to_chart(value)
+}
+
+/// Same as [`json_to_chart`] for a document that is already parsed.
+/// Documents of an older format version are upgraded by `load::migrate`
+/// before they get here
+pub fn value_to_chart(value: serde_json::Value) -> anyhow::Result<Chart> {
+    if let Some(version) = value.get("version").and_then(serde_json::Value::as_u64)
+        && version > u64::from(CHART_FORMAT_VERSION)
+    {
+        bail!("The chart has format version {version}, newer than the supported {CHART_FORMAT_VERSION}");
+    }
+    let chart: Chart = serde_json::from_value(value)
         .inspect_err(|e| error!("Error parsing json to chart: {e}"))?;
-    if chart.timing_map.bpm_changes.is_empty() {
-        bail!(
-            "The chart does not have a bpm change!"
-        )
-    };
+    check_chart(chart)
+}
+
+/// Shared by every chart format: log the diagnostics, refuse on errors,
+/// auto-fix warnings and build the timing map
+fn check_chart(mut chart: Chart) -> anyhow::Result<Chart> {
+    let diagnostics = chart.validate();
+    for d in &diagnostics {
+        match d.severity {
+            Severity::Error => error!("{d}"),
+            Severity::Warning => warn!("{d}"),
+        }
+    }
+    let errors: Vec<&ChartDiagnostic> = diagnostics.iter().filter(|d| d.severity == Severity::Error).collect();
+    if let Some(first) = errors.first() {
+        bail!("The chart has {} error(s), first: {first}", errors.len());
+    }
+    if !diagnostics.is_empty() {
+        chart.auto_fix();
+    }
+
+    chart.timing_map.build()
+        .inspect_err(|e| error!("Invalid timing map: {e}"))?;
     Ok(chart)
 }
 
@@ -62,18 +334,17 @@ mod tests {
     fn test_gen_chart() {
         let notes= vec![
             Note::Tap {
-                beat: Beat(1.0)
+                beat: Beat::from(1.0),
+                sound: None,
             },
             Note::Hold {
-                start: Beat(2.0),
-                end: Beat(3.0),
+                start: Beat::from(2.0),
+                end: Beat::from(3.0),
+                sound: None,
             }
         ];
         let tracks = vec![Track {id: 0, notes}];
-        let map = TimingMap {
-            offset: Time(0.0),
-            bpm_changes: vec![BpmChange{beat: Beat(0.0), bpm: 180.0}]
-        };
+        let map = TimingMap::new(Time(0.0), vec![BpmChange{beat: Beat::from(0.0), bpm: 180.0}]).unwrap();
         let chart = Chart {
             tracks,
             meta: ChartMeta{
@@ -82,10 +353,66 @@ mod tests {
                 level: 18
             },
             timing_map: map,
+            bgm: vec![],
         };
 
         let json_str = &chart.to_json().unwrap();
         println!("{json_str}");
         println!("{:?}", &chart);
     }
-}
\ No newline at end of file
+
+    fn chart_json(bpm_changes: &str, tracks: &str) -> String {
+        format!(r#"{{
+            "meta": {{"charter": "t", "level": 1, "desc": ""}},
+            "timing_map": {{"offset": 0.0, "bpm_changes": {bpm_changes}}},
+            "tracks": {tracks}
+        }}"#)
+    }
+
+    #[test]
+    fn test_validate_reports_diagnostics() {
+        let json = chart_json(
+            r#"[{"beat": 4.0, "bpm": 120.0}, {"beat": 0.0, "bpm": -1.0}]"#,
+            r#"[
+                {"id": 0, "notes": [{"Tap": {"beat": 2.0}}, {"Tap": {"beat": 1.0}}, {"Hold": {"start": 3.0, "end": 2.0}}]},
+                {"id": 0, "notes": []}
+            ]"#,
+        );
+        let chart: Chart = serde_json::from_str(&json).unwrap();
+        let diags = chart.validate();
+
+        let has = |severity: Severity, track: Option<u8>, note_idx: Option<usize>, text: &str| {
+            diags.iter().any(|d| d.severity == severity && d.track == track && d.note_idx == note_idx && d.message.contains(text))
+        };
+        assert!(has(Severity::Error, None, None, "Invalid bpm"));
+        assert!(has(Severity::Warning, None, None, "bpm changes are not sorted"));
+        assert!(has(Severity::Error, Some(0), Some(2), "before its start"));
+        assert!(has(Severity::Warning, Some(0), None, "not sorted"));
+        assert!(has(Severity::Error, Some(0), None, "Duplicate track id"));
+
+        assert!(json_to_chart(&json).is_err());
+    }
+
+    #[test]
+    fn test_json_to_chart_auto_fixes_warnings() {
+        let json = chart_json(
+            r#"[{"beat": 4.0, "bpm": 240.0}, {"beat": 0.0, "bpm": 120.0}]"#,
+            r#"[{"id": 0, "notes": [
+                {"Hold": {"start": 2.0, "end": 4.0}},
+                {"Tap": {"beat": 1.0}},
+                {"Tap": {"beat": 3.0}},
+                {"Tap": {"beat": 1.0}},
+                {"Hold": {"start": 5.0, "end": 5.0}},
+                {"Roll": {"start": 6.0, "end": 7.0}}
+            ]}]"#,
+        );
+        let chart = json_to_chart(&json).unwrap();
+
+        assert_eq!(chart.timing_map.bpm_changes()[0].beat, Beat::from(0.0));
+        let beats: Vec<f64> = chart.tracks[0].notes.iter().map(|n| n.beat().as_f64()).collect();
+        assert_eq!(beats, vec![1.0, 2.0, 5.0, 6.0]);
+        assert!(matches!(chart.tracks[0].notes[2], Note::Tap { .. }));
+        assert!(matches!(chart.tracks[0].notes[3], Note::Roll { .. }));
+        assert!(chart.validate().is_empty());
+    }
+}
@@ -0,0 +1,518 @@
+//! Line-oriented text chart format (`.mtc`), meant to be written by hand.
+//!
+//! ```text
+//! // comments run to the end of the line
+//! version 1
+//! charter someone
+//! level 7
+//! desc Hard
+//! offset -0.5
+//! bpm 0 120            // <beat> <bpm>
+//! stop 4 1             // <beat> <seconds>
+//! sv 8 0.5             // <beat> <multiplier>
+//! timesig 16 3/4       // <beat> <numerator>/<denominator>
+//! bgm 0 intro.wav      // <beat> <sound>
+//! lanes 0 1 2 3        // track ids, one column each
+//! notes                // optionally `notes <first measure>` for pickups
+//! 1000 ; 0:kick.wav
+//! 0M00
+//! 2000
+//! 3001
+//! ,
+//! 4000
+//! 3000
+//! ```
+//!
+//! After `notes`, measures are separated by `,` lines and their rows split the
+//! measure evenly, like StepMania. Cells: `0` empty, `1` tap, `2` hold head,
+//! `4` roll head, `3` hold/roll tail, `M` mine. Holds may cross measures.
+//! `; <column>:<sound>` after a row sets keysounds of the notes starting there.
+//! Beats are written as `2+1/3` or as decimals.
+
+use super::{check_chart, BgmEvent, Chart, ChartMeta, Note, Track, CHART_FORMAT_VERSION};
+use crate::core::timing::{Beat, BpmChange, ScrollVelocity, Stop, Time, TimeSignature, TimingMap};
+use anyhow::{bail, Context};
+use log::error;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub const TEXT_CHART_EXTENSION: &str = "mtc";
+
+/// 一小节最多的行数，超过说明拍子不是合理的分数 (通常来自浮点)
+const MAX_ROWS: i128 = 1920;
+
+#[derive(Default)]
+struct Header {
+    meta: Option<ChartMeta>,
+    offset: f64,
+    bpm_changes: Vec<BpmChange>,
+    stops: Vec<Stop>,
+    scroll_velocities: Vec<ScrollVelocity>,
+    time_signatures: Vec<TimeSignature>,
+    bgm: Vec<BgmEvent>,
+    lanes: Option<Vec<u8>>,
+}
+
+impl Header {
+    fn meta(&mut self) -> &mut ChartMeta {
+        self.meta.get_or_insert_with(|| ChartMeta { charter: String::new(), level: 0, desc: String::new() })
+    }
+
+    fn parse_line(&mut self, key: &str, rest: &str) -> anyhow::Result<()> {
+        match key {
+            "version" => {
+                let version: u32 = rest.parse().context("Invalid version")?;
+                if version > CHART_FORMAT_VERSION {
+                    bail!("The chart has format version {version}, newer than the supported {CHART_FORMAT_VERSION}");
+                }
+            }
+            "charter" => self.meta().charter = rest.to_string(),
+            "level" => self.meta().level = rest.parse().context("Invalid level")?,
+            "desc" => self.meta().desc = rest.to_string(),
+            "offset" => self.offset = rest.parse().context("Invalid offset")?,
+            "bpm" => {
+                let (beat, bpm) = beat_and_value(rest)?;
+                self.bpm_changes.push(BpmChange { beat, bpm });
+            }
+            "stop" => {
+                let (beat, secs) = beat_and_value(rest)?;
+                self.stops.push(Stop { beat, duration: Time(secs) });
+            }
+            "sv" => {
+                let (beat, multiplier) = beat_and_value(rest)?;
+                self.scroll_velocities.push(ScrollVelocity { beat, multiplier });
+            }
+            "timesig" => {
+                let (beat, sig) = rest.split_once(char::is_whitespace).context("Expected '<beat> <n>/<d>'")?;
+                let (n, d) = sig.trim().split_once('/').context("Expected '<beat> <n>/<d>'")?;
+                self.time_signatures.push(TimeSignature {
+                    beat: beat.parse()?,
+                    numerator: n.parse().context("Invalid numerator")?,
+                    denominator: d.parse().context("Invalid denominator")?,
+                });
+            }
+            "bgm" => {
+                let (beat, sound) = rest.split_once(char::is_whitespace).context("Expected '<beat> <sound>'")?;
+                self.bgm.push(BgmEvent { beat: beat.parse()?, sound: sound.trim().to_string() });
+            }
+            "lanes" => {
+                let lanes = rest.split_whitespace()
+                    .map(|id| id.parse().with_context(|| format!("Invalid track id '{id}'")))
+                    .collect::<anyhow::Result<Vec<u8>>>()?;
+                if lanes.is_empty() {
+                    bail!("No lanes given");
+                }
+                self.lanes = Some(lanes);
+            }
+            _ => bail!("Unknown header '{key}'"),
+        }
+        Ok(())
+    }
+
+    fn timing_map(&mut self) -> anyhow::Result<TimingMap> {
+        let mut map = TimingMap::new(Time(self.offset), std::mem::take(&mut self.bpm_changes))?;
+        map.set_stops(std::mem::take(&mut self.stops))?;
+        map.set_scroll_velocities(std::mem::take(&mut self.scroll_velocities))?;
+        map.set_time_signatures(std::mem::take(&mut self.time_signatures))?;
+        Ok(map)
+    }
+}
+
+/// `<beat> <number>`
+fn beat_and_value(rest: &str) -> anyhow::Result<(Beat, f64)> {
+    let mut parts = rest.split_whitespace();
+    let (Some(beat), Some(value), None) = (parts.next(), parts.next(), parts.next()) else {
+        bail!("Expected '<beat> <value>'");
+    };
+    Ok((beat.parse()?, value.parse().with_context(|| format!("Invalid value '{value}'"))?))
+}
+
+/// 去掉注释和首尾空白，返回 (行号, 内容)
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split("//").next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Parse a text chart, with the same checks as [`super::json_to_chart`]
+pub fn text_to_chart(text: &str) -> anyhow::Result<Chart> {
+    let chart = parse(text).inspect_err(|e| error!("Error parsing text chart: {e:#}"))?;
+    check_chart(chart)
+}
+
+fn parse(text: &str) -> anyhow::Result<Chart> {
+    let mut lines = content_lines(text);
+    let mut header = Header::default();
+
+    let first_measure: i64 = loop {
+        let Some((no, line)) = lines.next() else {
+            bail!("Missing 'notes' section");
+        };
+        let (key, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if key == "notes" {
+            break if rest.is_empty() { 0 } else {
+                rest.trim().parse().with_context(|| format!("Line {no}: invalid first measure '{rest}'"))?
+            };
+        }
+        header.parse_line(key, rest.trim()).with_context(|| format!("Line {no}: '{line}'"))?;
+    };
+
+    let meta = header.meta.take().context("Missing charter/level/desc")?;
+    let timing_map = header.timing_map()?;
+
+    let mut measures: Vec<Vec<(usize, &str)>> = vec![vec![]];
+    for (no, line) in lines {
+        if line == "," {
+            measures.push(vec![]);
+        } else {
+            measures.last_mut().unwrap().push((no, line));
+        }
+    }
+
+    let lane_ids = match header.lanes.take() {
+        Some(lanes) => lanes,
+        None => {
+            let (_, row) = measures.iter().flatten().next().context("No lanes and no rows to infer them from")?;
+            let columns = row.split(';').next().unwrap_or("").trim().chars().count();
+            (0..columns)
+                .map(u8::try_from)
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("{columns} columns do not fit in lane ids"))?
+        }
+    };
+    let mut tracks: Vec<Track> = lane_ids.iter().map(|&id| Track { id, notes: vec![] }).collect();
+    // 每列尚未闭合的长条头 (起始拍, 是否 roll, keysound)
+    let mut heads: Vec<Option<(Beat, bool, Option<String>)>> = vec![None; tracks.len()];
+
+    for (k, rows) in measures.iter().enumerate() {
+        let measure = first_measure + k as i64;
+        let start = ratio(timing_map.measure_to_beat(measure));
+        let len = sub(ratio(timing_map.measure_to_beat(measure + 1)), start);
+        let n = rows.len() as i128;
+
+        for (i, &(no, row)) in rows.iter().enumerate() {
+            let beat = to_beat(add(start, mul(len, (i as i128, n))))
+                .with_context(|| format!("Line {no}: row beat out of range"))?;
+            parse_row(row, beat, &mut tracks, &mut heads).with_context(|| format!("Line {no}: '{row}'"))?;
+        }
+    }
+
+    if let Some(col) = heads.iter().position(Option::is_some) {
+        bail!("Hold in column {col} is never closed");
+    }
+    for track in &mut tracks {
+        track.notes.sort_by_key(|n| n.beat());
+    }
+
+    Ok(Chart { meta, timing_map, tracks, bgm: header.bgm })
+}
+
+fn parse_row(
+    row: &str,
+    beat: Beat,
+    tracks: &mut [Track],
+    heads: &mut [Option<(Beat, bool, Option<String>)>],
+) -> anyhow::Result<()> {
+    let (cells, sounds) = row.split_once(';').unwrap_or((row, ""));
+    let cells: Vec<char> = cells.trim().chars().collect();
+    if cells.len() != tracks.len() {
+        bail!("Row has {} columns, expected {}", cells.len(), tracks.len());
+    }
+
+    let mut sounds: HashMap<usize, String> = sounds.split_whitespace()
+        .map(|s| {
+            let (col, sound) = s.split_once(':').with_context(|| format!("Expected '<column>:<sound>', got '{s}'"))?;
+            let col: usize = col.parse().with_context(|| format!("Invalid column '{col}'"))?;
+            if !matches!(cells.get(col), Some('1' | '2' | '4')) {
+                bail!("Keysound for column {col} without a note starting there");
+            }
+            Ok((col, sound.to_string()))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    for (col, &c) in cells.iter().enumerate() {
+        let sound = sounds.remove(&col);
+        let notes = &mut tracks[col].notes;
+        match c {
+            '0' => {}
+            '1' => notes.push(Note::Tap { beat, sound }),
+            'M' => notes.push(Note::Mine { beat }),
+            '2' | '4' => {
+                if heads[col].is_some() {
+                    bail!("Column {col} starts a hold before closing the previous one");
+                }
+                heads[col] = Some((beat, c == '4', sound));
+            }
+            '3' => match heads[col].take() {
+                Some((start, false, sound)) => notes.push(Note::Hold { start, end: beat, sound }),
+                Some((start, true, sound)) => notes.push(Note::Roll { start, end: beat, sound }),
+                None => bail!("Tail without a head in column {col}"),
+            },
+            _ => bail!("Unknown cell '{c}' in column {col}"),
+        }
+    }
+    Ok(())
+}
+
+/// Write `chart` in the text format, fails if something cannot be
+/// represented (line breaks in text, two notes in one cell, beats that need
+/// more than [`MAX_ROWS`] rows per measure)
+pub fn chart_to_text(chart: &Chart) -> anyhow::Result<String> {
+    let single_line = |s: &str, what: &str| {
+        if s.contains('\n') || s.contains("//") {
+            bail!("The {what} '{s}' cannot be written on a single line");
+        }
+        Ok(())
+    };
+    single_line(&chart.meta.charter, "charter")?;
+    single_line(&chart.meta.desc, "description")?;
+
+    let map = &chart.timing_map;
+    let mut out = String::new();
+    writeln!(out, "version {CHART_FORMAT_VERSION}")?;
+    writeln!(out, "charter {}", chart.meta.charter)?;
+    writeln!(out, "level {}", chart.meta.level)?;
+    writeln!(out, "desc {}", chart.meta.desc)?;
+    writeln!(out, "offset {}", map.offset().0)?;
+    for c in map.bpm_changes() {
+        writeln!(out, "bpm {} {}", c.beat, c.bpm)?;
+    }
+    for s in map.stops() {
+        writeln!(out, "stop {} {}", s.beat, s.duration.0)?;
+    }
+    for sv in map.scroll_velocities() {
+        writeln!(out, "sv {} {}", sv.beat, sv.multiplier)?;
+    }
+    for ts in map.time_signatures() {
+        writeln!(out, "timesig {} {}/{}", ts.beat, ts.numerator, ts.denominator)?;
+    }
+    for e in &chart.bgm {
+        single_line(&e.sound, "sound")?;
+        writeln!(out, "bgm {} {}", e.beat, e.sound)?;
+    }
+    let ids: Vec<String> = chart.tracks.iter().map(|t| t.id.to_string()).collect();
+    writeln!(out, "lanes {}", ids.join(" "))?;
+
+    // (拍, 列, 字符, keysound)
+    let mut events: Vec<(Beat, usize, char, Option<&str>)> = vec![];
+    for (col, track) in chart.tracks.iter().enumerate() {
+        for note in &track.notes {
+            if let Some(sound) = note.sound()
+                && (sound.is_empty() || sound.contains(char::is_whitespace) || sound.contains("//"))
+            {
+                bail!("The keysound '{sound}' cannot be written in a row");
+            }
+            match note {
+                Note::Tap { beat, .. } => events.push((*beat, col, '1', note.sound())),
+                Note::Mine { beat } => events.push((*beat, col, 'M', None)),
+                Note::Hold { start, end, .. } | Note::Roll { start, end, .. } if start == end => {
+                    events.push((*start, col, '1', note.sound()));
+                }
+                Note::Hold { start, end, .. } | Note::Roll { start, end, .. } => {
+                    let head = if matches!(note, Note::Roll { .. }) { '4' } else { '2' };
+                    events.push((*start, col, head, note.sound()));
+                    events.push((*end, col, '3', None));
+                }
+            }
+        }
+    }
+
+    let first_beat = events.iter().map(|e| e.0).min().unwrap_or(Beat::ZERO);
+    let last_beat = events.iter().map(|e| e.0).max().unwrap_or(Beat::ZERO);
+    let mut first = map.beat_to_measure(&first_beat).0.min(0);
+    // beat_to_measure 用浮点，边界上可能差一小节
+    while map.measure_to_beat(first) > first_beat {
+        first -= 1;
+    }
+    let mut starts = vec![map.measure_to_beat(first)];
+    while *starts.last().unwrap() <= last_beat {
+        starts.push(map.measure_to_beat(first + starts.len() as i64));
+    }
+    let mut by_measure: Vec<Vec<_>> = vec![vec![]; starts.len() - 1];
+    for e in events {
+        by_measure[starts.partition_point(|s| *s <= e.0) - 1].push(e);
+    }
+
+    if first == 0 {
+        writeln!(out, "notes")?;
+    } else {
+        writeln!(out, "notes {first}")?;
+    }
+    for (k, events) in by_measure.iter().enumerate() {
+        if k > 0 {
+            writeln!(out, ",")?;
+        }
+        let start = ratio(starts[k]);
+        let len = sub(ratio(starts[k + 1]), start);
+        let positions: Vec<(i128, i128)> = events.iter().map(|e| div(sub(ratio(e.0), start), len)).collect();
+        // 各位置分母的最小公倍数，超过上限立刻停下，免得溢出
+        let rows = positions.iter().try_fold(1, |n: i128, &(_, q)| {
+            n.checked_mul(q / gcd(n, q)).filter(|&rows| rows <= MAX_ROWS)
+        });
+        let Some(rows) = rows else {
+            bail!("Measure {} would need more than {MAX_ROWS} rows", first + k as i64);
+        };
+
+        let mut grid = vec![vec!['0'; chart.tracks.len()]; rows as usize];
+        let mut sounds: Vec<Vec<String>> = vec![vec![]; rows as usize];
+        for (&(beat, col, c, sound), &(p, q)) in events.iter().zip(&positions) {
+            let row = (p * rows / q) as usize;
+            if grid[row][col] != '0' {
+                bail!("Two notes at beat {beat} in column {col}");
+            }
+            grid[row][col] = c;
+            if let Some(sound) = sound {
+                sounds[row].push(format!("{col}:{sound}"));
+            }
+        }
+        for (cells, sounds) in grid.iter().zip(&sounds) {
+            let cells: String = cells.iter().collect();
+            if sounds.is_empty() {
+                writeln!(out, "{cells}")?;
+            } else {
+                writeln!(out, "{cells} ; {}", sounds.join(" "))?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+// --- 分数运算，(分子, 分母)，分母恒为正 ---
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+fn reduce((p, q): (i128, i128)) -> (i128, i128) {
+    let g = gcd(p, q).max(1) * q.signum();
+    (p / g, q / g)
+}
+
+fn ratio(beat: Beat) -> (i128, i128) {
+    let den = beat.denominator() as i128;
+    (beat.whole() as i128 * den + beat.numerator() as i128, den)
+}
+
+fn add(a: (i128, i128), b: (i128, i128)) -> (i128, i128) {
+    reduce((a.0 * b.1 + b.0 * a.1, a.1 * b.1))
+}
+
+fn sub(a: (i128, i128), b: (i128, i128)) -> (i128, i128) {
+    add(a, (-b.0, b.1))
+}
+
+fn mul(a: (i128, i128), b: (i128, i128)) -> (i128, i128) {
+    reduce((a.0 * b.0, a.1 * b.1))
+}
+
+fn div(a: (i128, i128), b: (i128, i128)) -> (i128, i128) {
+    reduce((a.0 * b.1, a.1 * b.0))
+}
+
+fn to_beat((p, q): (i128, i128)) -> anyhow::Result<Beat> {
+    let whole = i64::try_from(p.div_euclid(q))?;
+    let num = u32::try_from(p.rem_euclid(q))?;
+    Beat::try_new(whole, num, u32::try_from(q)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::json_to_chart;
+
+    const JSON: &str = r#"{
+        "meta": {"charter": "someone", "level": 7, "desc": "Hard - 3/4 bridge"},
+        "timing_map": {
+            "offset": -0.25,
+            "bpm_changes": [{"beat": 0.0, "bpm": 150.0}, {"beat": 6.0, "bpm": 175.5}],
+            "stops": [{"beat": 2.0, "duration": 0.5}],
+            "scroll_velocities": [{"beat": 4.0, "multiplier": 0.5}],
+            "time_signatures": [{"beat": 8.0, "numerator": 3, "denominator": 4}]
+        },
+        "tracks": [
+            {"id": 0, "notes": [{"Tap": {"beat": 0.0, "sound": "kick.wav"}}, {"Hold": {"start": 3.0, "end": 9.5, "sound": "pad.wav"}}]},
+            {"id": 1, "notes": [{"Tap": {"beat": [0, 1, 3]}}, {"Tap": {"beat": [0, 2, 3]}}, {"Mine": {"beat": 5.0}}]},
+            {"id": 2, "notes": [{"Roll": {"start": 1.0, "end": 2.75}}, {"Tap": {"beat": 10.0}}]},
+            {"id": 5, "notes": [{"Tap": {"beat": [11, 1, 6]}}]}
+        ],
+        "bgm": [{"beat": 0.0, "sound": "intro loop.wav"}, {"beat": 8.0, "sound": "bridge.wav"}]
+    }"#;
+
+    #[test]
+    fn test_text_round_trip() {
+        let chart = json_to_chart(JSON).unwrap();
+        let text = chart_to_text(&chart).unwrap();
+        let parsed = text_to_chart(&text).unwrap();
+        assert_eq!(parsed.to_value().unwrap(), chart.to_value().unwrap(), "\n{text}");
+        // 文本 -> 谱面 -> 文本也不变
+        assert_eq!(chart_to_text(&parsed).unwrap(), text);
+
+        // 3/4 小节从第 8 拍开始，第 11+1/6 拍在其中 (11+1/6 - 11) / 3 = 1/18 处
+        let measures: Vec<&str> = text.split("\n,\n").collect();
+        assert_eq!(measures.len(), 4);
+        assert_eq!(measures[3].lines().count(), 18);
+    }
+
+    #[test]
+    fn test_parse_hand_written() {
+        let text = "
+            // 两小节的小谱面
+            charter me
+            level 3
+            desc
+            bpm 0 120
+            notes -1
+            0001        // 弱起小节
+            ,
+            2000 ; 0:a.wav
+            0M00
+            0100 ; 1:b.wav
+            0000
+            ,
+            3000
+        ";
+        let chart = text_to_chart(text).unwrap();
+        assert_eq!(chart.tracks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(chart.tracks[3].notes, vec![Note::Tap { beat: Beat::from_int(-4), sound: None }]);
+        assert_eq!(chart.tracks[0].notes, vec![
+            Note::Hold { start: Beat::ZERO, end: Beat::from_int(4), sound: Some("a.wav".into()) },
+        ]);
+        assert_eq!(chart.tracks[1].notes, vec![
+            Note::Mine { beat: Beat::from_int(1) },
+            Note::Tap { beat: Beat::from_int(2), sound: Some("b.wav".into()) },
+        ]);
+        assert_eq!(chart.meta.desc, "");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let header = "charter c\nlevel 1\ndesc d\nbpm 0 120\nlanes 0 1\n";
+        assert!(text_to_chart(&format!("{header}notes\n10\n")).is_ok());
+        for (body, why) in [
+            ("notes\n100\n", "wrong column count"),
+            ("notes\n30\n", "tail without head"),
+            ("notes\nX0\n", "unknown cell"),
+            ("notes\n10 ; 1:a.wav\n", "keysound on an empty cell"),
+            ("notes\n20\n20\n30\n", "hold restarted"),
+            ("notes\n20\n", "unclosed hold"),
+        ] {
+            assert!(text_to_chart(&format!("{header}{body}")).is_err(), "{why}");
+        }
+        assert!(text_to_chart("charter c\nlevel 1\ndesc d\nnotes\n1\n").is_err(), "no bpm");
+        assert!(text_to_chart("version 99\nnotes\n").is_err());
+        assert!(text_to_chart(&format!("{header}speed 2\nnotes\n")).is_err());
+        let wide = "0".repeat(300);
+        assert!(text_to_chart(&format!("charter c\nlevel 1\ndesc d\nbpm 0 120\nnotes\n{wide}\n")).is_err());
+    }
+
+    #[test]
+    fn test_too_many_rows_does_not_overflow() {
+        let mut chart = json_to_chart(JSON).unwrap();
+        // 几个接近 u32 上限的质数分母，最小公倍数会超出 i128
+        let primes = [4294967291, 4294967279, 4294967231, 4294967197, 4294967189];
+        chart.tracks[0].notes = primes.iter().map(|&p| Note::Tap { beat: Beat::new(0, 1, p), sound: None }).collect();
+        let err = chart_to_text(&chart).unwrap_err();
+        assert!(err.to_string().contains("rows"), "{err}");
+    }
+}
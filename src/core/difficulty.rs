@@ -0,0 +1,189 @@
+//! Strain-based difficulty estimation.
+//!
+//! Every note adds strain to its lane (jacks), its hand and the whole chart
+//! (chords, density); strain decays exponentially between notes, so it is
+//! measured in real time and BPM is accounted for through the timing map.
+//! Notes landing while a hold or roll in another lane is still down weigh more.
+//! The highest strain of each section is taken and the peaks are summed
+//! with decreasing weights, hardest first.
+
+use crate::core::chart::Chart;
+
+/// Strain left after one second, per lane
+const INDIVIDUAL_DECAY: f64 = 0.125;
+/// Strain left after one second, per hand and overall
+const HAND_DECAY: f64 = 0.3;
+const OVERALL_DECAY: f64 = 0.3;
+/// Same-lane repeats closer than this get an extra jack bonus
+const JACK_THRESHOLD: f64 = 0.25;
+/// Multiplier for notes pressed while another lane holds
+const HOLD_BONUS: f64 = 1.25;
+const SECTION_LENGTH: f64 = 0.4;
+const PEAK_WEIGHT: f64 = 0.9;
+/// Maps the weighted strain sum onto the charter level range
+const RATING_SCALE: f64 = 0.07;
+
+struct TimedNote {
+    start: f64,
+    end: f64,
+    lane: usize,
+}
+
+/// Estimated difficulty of the chart, on the scale of `ChartMeta.level`
+pub fn estimate_difficulty(chart: &Chart) -> f64 {
+    let map = &chart.timing_map;
+    let lane_count = chart.tracks.len();
+    if lane_count == 0 {
+        return 0.0;
+    }
+
+    let mut notes: Vec<TimedNote> = chart.tracks.iter().enumerate()
+        .flat_map(|(lane, track)| track.notes.iter().map(move |note| (lane, note)))
+        .filter(|(_, note)| !note.is_mine())
+        .map(|(lane, note)| {
+            let start = map.beat_to_time(&note.beat()).0;
+            let end = map.beat_to_time(&note.end_beat()).0;
+            TimedNote { start, end, lane }
+        })
+        .collect();
+    if notes.is_empty() {
+        return 0.0;
+    }
+    notes.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    // 左右手：奇数键位时中间轨道归右手
+    let hand_of = |lane: usize| usize::from(lane * 2 >= lane_count);
+
+    let mut individual = vec![0.0; lane_count];
+    let mut last_in_lane: Vec<Option<f64>> = vec![None; lane_count];
+    let mut hand = [0.0; 2];
+    let mut overall = 0.0;
+    let mut hold_ends = vec![f64::NEG_INFINITY; lane_count];
+
+    let mut prev_time = notes[0].start;
+    let mut section_end = notes[0].start + SECTION_LENGTH;
+    let mut section_peak: f64 = 0.0;
+    let mut peaks = Vec::new();
+
+    for note in &notes {
+        while note.start >= section_end {
+            peaks.push(section_peak);
+            section_peak = 0.0;
+            section_end += SECTION_LENGTH;
+        }
+
+        let dt = note.start - prev_time;
+        prev_time = note.start;
+        for s in individual.iter_mut() {
+            *s *= INDIVIDUAL_DECAY.powf(dt);
+        }
+        for s in hand.iter_mut() {
+            *s *= HAND_DECAY.powf(dt);
+        }
+        overall *= OVERALL_DECAY.powf(dt);
+
+        let held_elsewhere = hold_ends.iter().enumerate()
+            .any(|(lane, &end)| lane != note.lane && end > note.start);
+        let hold_factor = if held_elsewhere { HOLD_BONUS } else { 1.0 };
+
+        let jack_factor = match last_in_lane[note.lane] {
+            Some(last) if note.start - last < JACK_THRESHOLD => {
+                1.0 + (JACK_THRESHOLD - (note.start - last)) / JACK_THRESHOLD
+            }
+            _ => 1.0,
+        };
+        last_in_lane[note.lane] = Some(note.start);
+        if note.end > note.start {
+            hold_ends[note.lane] = note.end;
+        }
+
+        individual[note.lane] += 2.0 * hold_factor * jack_factor;
+        hand[hand_of(note.lane)] += hold_factor;
+        overall += hold_factor;
+
+        let strain = individual[note.lane] + hand[hand_of(note.lane)] + overall;
+        section_peak = section_peak.max(strain);
+    }
+    peaks.push(section_peak);
+
+    peaks.sort_by(|a, b| b.total_cmp(a));
+    let mut weight = 1.0;
+    let mut total = 0.0;
+    for peak in peaks {
+        total += peak * weight;
+        weight *= PEAK_WEIGHT;
+    }
+    total * RATING_SCALE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{ChartMeta, Note, Track};
+    use crate::core::timing::{Beat, BpmChange, Time, TimingMap};
+
+    fn chart(bpm: f64, tracks: Vec<Vec<Note>>) -> Chart {
+        Chart {
+            meta: ChartMeta { charter: "t".into(), level: 0, desc: String::new() },
+            timing_map: TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm }]).unwrap(),
+            tracks: tracks.into_iter().enumerate()
+                .map(|(id, notes)| Track { id: id as u8, notes })
+                .collect(),
+            bgm: vec![],
+        }
+    }
+
+    /// 每拍 `per_beat` 个音符，按 `lanes` 顺序循环放置
+    fn stream(bpm: f64, per_beat: u32, beats: i64, lanes: &[usize]) -> Chart {
+        let mut tracks = vec![vec![]; 4];
+        for (i, b) in (0..beats * per_beat as i64).enumerate() {
+            let beat = Beat::new(b / per_beat as i64, (b % per_beat as i64) as u32, per_beat);
+            tracks[lanes[i % lanes.len()]].push(Note::Tap { beat, sound: None });
+        }
+        chart(bpm, tracks)
+    }
+
+    #[test]
+    fn test_empty_chart_is_zero() {
+        assert_eq!(estimate_difficulty(&chart(120.0, vec![vec![], vec![]])), 0.0);
+    }
+
+    #[test]
+    fn test_faster_is_harder() {
+        let slow = estimate_difficulty(&stream(120.0, 2, 64, &[0, 1, 2, 3]));
+        let fast = estimate_difficulty(&stream(180.0, 4, 64, &[0, 1, 2, 3]));
+        assert!(fast > slow, "{fast} <= {slow}");
+    }
+
+    #[test]
+    fn test_jacks_are_harder_than_rolls() {
+        let roll = estimate_difficulty(&stream(150.0, 4, 32, &[0, 1, 2, 3]));
+        let jack = estimate_difficulty(&stream(150.0, 4, 32, &[0]));
+        assert!(jack > roll, "{jack} <= {roll}");
+    }
+
+    #[test]
+    fn test_one_hand_is_harder_than_alternating() {
+        let alternating = estimate_difficulty(&stream(150.0, 4, 32, &[0, 2, 1, 3]));
+        let one_hand = estimate_difficulty(&stream(150.0, 4, 32, &[0, 1]));
+        assert!(one_hand > alternating, "{one_hand} <= {alternating}");
+    }
+
+    #[test]
+    fn test_chords_and_holds_add_difficulty() {
+        let single = stream(150.0, 2, 32, &[0, 1, 2, 3]);
+        let mut chorded = single.clone();
+        let extra: Vec<Note> = chorded.tracks[0].notes.iter()
+            .map(|n| Note::Tap { beat: n.beat() + Beat::new(0, 1, 2), sound: None })
+            .collect();
+        chorded.tracks[3].notes.extend(extra);
+        chorded.tracks[3].notes.sort_by_key(|n| n.beat());
+        assert!(estimate_difficulty(&chorded) > estimate_difficulty(&single));
+
+        let mut held = single.clone();
+        held.tracks[0].notes = vec![Note::Hold { start: Beat::ZERO, end: Beat::from_int(32), sound: None }];
+        let mut without = single.clone();
+        without.tracks[0].notes = vec![Note::Tap { beat: Beat::ZERO, sound: None }];
+        assert!(estimate_difficulty(&held) > estimate_difficulty(&without));
+    }
+}
@@ -8,51 +8,523 @@
 //! └─────┬──────┘
 //!       │
 //! ┌─────▼──────┐
-//! │ JudgeCore  │   ← Perfect / Good / Miss
+//! │ JudgeCore  │   ← tier / Miss
 //! └────────────┘
 //! ```
 
+use anyhow::bail;
 use serde::{Deserialize, Serialize};
-use crate::core::chart::{Note, Track};
-use crate::core::timing::{Time, TimingMap};
+use crate::core::chart::{Note, NoteKind, Track};
+use crate::core::timing::{Beat, Time, TimingMap};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum JudgeResult {
-    Perfect(Time),
-    Good(Time),
+    /// `tier` indexes [`JudgeWindow::tiers`], `delta` < 0 is early
+    Hit { tier: usize, delta: Time },
+    /// Pressed too early for any tier but inside [`JudgeWindow::poor`]:
+    /// breaks combo, the note stays pending
+    Poor { delta: Time },
     Miss,
+    MineHit, // 踩雷，断 combo
+    /// A hold tick passed, `held` if the key was down, see [`JudgeCore::hold_tick`]
+    Tick { held: bool },
+    /// A press with no judgeable note, see [`JudgeCore::ghost_tap`]
+    Empty,
 }
 
-/// |delta| <= perfect  -> Perfect
-/// |delta| <= good     -> Good
-/// else                 Miss
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl JudgeResult {
+    /// Offset from the judged point, `None` when there was no timed input
+    pub fn delta(&self) -> Option<Time> {
+        match *self {
+            JudgeResult::Hit { delta, .. } | JudgeResult::Poor { delta } => Some(delta),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of a note a judgment belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JudgePhase {
+    /// The press on a tap, mine or the start of a hold/roll; ghost taps too
+    Head,
+    /// The end of a hold/roll, or the whole hold when its tail is not judged separately
+    Tail,
+    Tick,
+}
+
+/// One judgment with its context, see [`JudgeManager::drain_events`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct JudgeEvent {
+    pub track: u8,
+    /// `None` for a ghost tap
+    pub note_idx: Option<usize>,
+    pub kind: Option<NoteKind>,
+    /// Time of the judged point: the start for heads, the end for tails, the tick itself
+    pub note_time: Option<Time>,
+    /// `None` when judged by time passing (misses, holds held through)
+    pub input_time: Option<Time>,
+    pub delta: Option<Time>,
+    pub result: JudgeResult,
+    pub phase: JudgePhase,
+}
+
+impl JudgeEvent {
+    fn new(track: u8, idx: usize, kind: NoteKind, phase: JudgePhase, note_time: Time, result: JudgeResult, input_time: Option<Time>) -> Self {
+        Self {
+            track,
+            note_idx: Some(idx),
+            kind: Some(kind),
+            note_time: Some(note_time),
+            input_time,
+            delta: result.delta(),
+            result,
+            phase,
+        }
+    }
+
+    /// 窗口外的按下：Poor 属于 `idx` 音符，空按不属于任何音符
+    fn miss_press(track: u8, idx: usize, kind: NoteKind, note_time: Time, result: JudgeResult, input_time: Time) -> Self {
+        match result {
+            JudgeResult::Empty => Self::empty(track, input_time),
+            _ => Self::new(track, idx, kind, JudgePhase::Head, note_time, result, Some(input_time)),
+        }
+    }
+
+    fn empty(track: u8, input_time: Time) -> Self {
+        Self {
+            track,
+            note_idx: None,
+            kind: None,
+            note_time: None,
+            input_time: Some(input_time),
+            delta: None,
+            result: JudgeResult::Empty,
+            phase: JudgePhase::Head,
+        }
+    }
+}
+
+/// What a tier does to the combo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComboEffect {
+    Continue,
+    Break,
+}
+
+/// `-early <= delta <= late`; `window` in config sets both bounds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "JudgeTierRepr")]
+pub struct JudgeTier {
+    pub name: String,
+    pub early: Time,
+    pub late: Time,
+    /// Fraction of a note's full score, 1.0 for the best tier
+    pub weight: f64,
+    pub combo: ComboEffect,
+    /// Color name or `#rrggbb`, parsed by the UI
+    pub color: String,
+}
+
+#[derive(Deserialize)]
+struct JudgeTierRepr {
+    name: String,
+    window: Option<Time>,
+    early: Option<Time>,
+    late: Option<Time>,
+    weight: f64,
+    combo: ComboEffect,
+    color: String,
+}
+
+impl TryFrom<JudgeTierRepr> for JudgeTier {
+    type Error = String;
+
+    fn try_from(r: JudgeTierRepr) -> Result<Self, Self::Error> {
+        let (Some(early), Some(late)) = (r.early.or(r.window), r.late.or(r.window)) else {
+            return Err(format!("tier {} needs a window or both early and late", r.name));
+        };
+        Ok(Self { name: r.name, early, late, weight: r.weight, combo: r.combo, color: r.color })
+    }
+}
+
+impl JudgeTier {
+    /// Same bound on both sides
+    fn new(name: &str, window: f64, weight: f64, combo: ComboEffect, color: &str) -> Self {
+        Self { name: name.into(), early: Time(window), late: Time(window), weight, combo, color: color.into() }
+    }
+
+    fn contains(&self, delta: Time) -> bool {
+        -self.early.0 <= delta.0 && delta.0 <= self.late.0
+    }
+}
+
+/// Tiers ordered from the tightest window to the widest, a hit outside the
+/// last one is a Miss. Written in config as a preset name, as
+/// `{"tiers": [...], "poor": ..}` or as the old `{"perfect": .., "good": ..}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "JudgeWindowRepr", into = "JudgeWindowRepr")]
 pub struct JudgeWindow {
-    pub perfect: Time,
-    pub good: Time,
+    tiers: Vec<JudgeTier>,
+    /// 早于所有档位但在此范围内的按键判 Poor，不消耗音符
+    poor: Option<Time>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum JudgeWindowRepr {
+    Preset(JudgePreset),
+    Tiers {
+        tiers: Vec<JudgeTier>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        poor: Option<Time>,
+    },
+    Classic { perfect: Time, good: Time },
+}
+
+/// Tier sets mimicking common games
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JudgePreset {
+    /// PERFECT / GOOD
+    Classic,
+    /// MARVELOUS / PERFECT / GREAT / GOOD / BOO
+    StepMania,
+    /// P-GREAT / GREAT / GOOD / BAD
+    Iidx,
+    /// 320 / 300 / 200 / 100 / 50 at OD 8
+    Osu,
+}
+
+impl JudgePreset {
+    /// Early-press Poor window, see [`JudgeResult::Poor`]
+    pub fn poor(self) -> Option<Time> {
+        match self {
+            JudgePreset::Iidx => Some(Time(0.35)),
+            _ => None,
+        }
+    }
+
+    pub fn tiers(self) -> Vec<JudgeTier> {
+        use ComboEffect::*;
+        match self {
+            JudgePreset::Classic => JudgeWindow::classic(Time(0.08), Time(0.16)).tiers,
+            JudgePreset::StepMania => vec![
+                JudgeTier::new("MARVELOUS", 0.0225, 1.0, Continue, "lightcyan"),
+                JudgeTier::new("PERFECT", 0.045, 0.9, Continue, "lightyellow"),
+                JudgeTier::new("GREAT", 0.090, 0.6, Continue, "lightgreen"),
+                JudgeTier::new("GOOD", 0.135, 0.2, Break, "lightblue"),
+                JudgeTier::new("BOO", 0.180, 0.0, Break, "magenta"),
+            ],
+            JudgePreset::Iidx => vec![
+                JudgeTier::new("P-GREAT", 0.0167, 1.0, Continue, "lightcyan"),
+                JudgeTier::new("GREAT", 0.0333, 0.5, Continue, "lightyellow"),
+                JudgeTier::new("GOOD", 0.1167, 0.0, Continue, "lightgreen"),
+                JudgeTier::new("BAD", 0.2500, 0.0, Break, "magenta"),
+            ],
+            JudgePreset::Osu => vec![
+                JudgeTier::new("320", 0.016, 1.0, Continue, "lightcyan"),
+                JudgeTier::new("300", 0.040, 0.9375, Continue, "lightyellow"),
+                JudgeTier::new("200", 0.073, 0.625, Continue, "lightgreen"),
+                JudgeTier::new("100", 0.103, 0.3125, Continue, "lightblue"),
+                JudgeTier::new("50", 0.127, 0.15625, Continue, "gray"),
+            ],
+        }
+    }
+}
+
+impl TryFrom<JudgeWindowRepr> for JudgeWindow {
+    type Error = String;
+
+    fn try_from(repr: JudgeWindowRepr) -> Result<Self, Self::Error> {
+        match repr {
+            JudgeWindowRepr::Preset(preset) => Ok(preset.into()),
+            JudgeWindowRepr::Tiers { tiers, poor } => Self::new(tiers, poor).map_err(|e| e.to_string()),
+            JudgeWindowRepr::Classic { perfect, good } => Ok(Self::classic(perfect, good)),
+        }
+    }
+}
+
+impl From<JudgeWindow> for JudgeWindowRepr {
+    fn from(window: JudgeWindow) -> Self {
+        JudgeWindowRepr::Tiers { tiers: window.tiers, poor: window.poor }
+    }
+}
+
+impl From<JudgePreset> for JudgeWindow {
+    fn from(preset: JudgePreset) -> Self {
+        Self { tiers: preset.tiers(), poor: preset.poor() }
+    }
+}
+
+impl JudgeWindow {
+    pub fn new(tiers: Vec<JudgeTier>, poor: Option<Time>) -> anyhow::Result<Self> {
+        if tiers.is_empty() {
+            bail!("A judge window needs at least one tier");
+        }
+        let valid = |t: Time| t.0.is_finite() && t.0 >= 0.0;
+        if let Some(t) = tiers.iter().find(|t| !(valid(t.early) && valid(t.late))) {
+            bail!("Invalid window -{}/+{} of tier {}", t.early.0, t.late.0, t.name);
+        }
+        if let Some(t) = tiers.iter().find(|t| !(0.0..=1.0).contains(&t.weight)) {
+            bail!("Weight {} of tier {} is not within 0..=1", t.weight, t.name);
+        }
+        if let Some(pair) = tiers.windows(2).find(|p| p[1].early < p[0].early || p[1].late < p[0].late) {
+            bail!("Tier {} is tighter than {}, tiers must widen", pair[1].name, pair[0].name);
+        }
+        let window = Self { tiers, poor };
+        if let Some(poor) = poor
+            && !(valid(poor) && poor >= window.max_early())
+        {
+            bail!("Poor window {} must be at least the widest early bound {}", poor.0, window.max_early().0);
+        }
+        Ok(window)
+    }
+
+    /// The original two-tier window: PERFECT for 1000, GOOD for 500
+    pub fn classic(perfect: Time, good: Time) -> Self {
+        Self {
+            tiers: vec![
+                JudgeTier::new("PERFECT", perfect.0, 1.0, ComboEffect::Continue, "lightyellow"),
+                JudgeTier::new("GOOD", good.0, 0.5, ComboEffect::Continue, "green"),
+            ],
+            poor: None,
+        }
+    }
+
+    pub fn tiers(&self) -> &[JudgeTier] {
+        &self.tiers
+    }
+
+    pub fn poor(&self) -> Option<Time> {
+        self.poor
+    }
+
+    /// Widest early bound, an earlier press does not touch the note
+    pub fn max_early(&self) -> Time {
+        self.tiers.last().map_or(Time(0.0), |t| t.early)
+    }
+
+    /// Widest late bound, after it an unplayed note is missed
+    pub fn max_late(&self) -> Time {
+        self.tiers.last().map_or(Time(0.0), |t| t.late)
+    }
+
+    /// Whether `delta` falls into any tier
+    pub fn contains(&self, delta: Time) -> bool {
+        self.tiers.last().is_some_and(|t| t.contains(delta))
+    }
+
+    fn tier_of(&self, delta: Time) -> Option<usize> {
+        self.tier_within(delta, 1.0)
+    }
+
+    /// Like `tier_of` with every bound multiplied by `scale`
+    fn tier_within(&self, delta: Time, scale: f64) -> Option<usize> {
+        self.tiers.iter().position(|t| -t.early.0 * scale <= delta.0 && delta.0 <= t.late.0 * scale)
+    }
+
+    /// Too early for a tier, but inside the Poor window
+    fn is_poor(&self, delta: Time) -> bool {
+        self.poor.is_some_and(|poor| delta.0 < -self.max_early().0 && -delta.0 <= poor.0)
+    }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct JudgeCore {
     pub window: JudgeWindow,
     pub hold_tolerance: Time,
+    /// |delta| <= mine_window 时按下即踩雷
+    #[serde(default = "default_mine_window")]
+    pub mine_window: Time,
+    /// Roll 两次敲击之间允许的最长间隔
+    #[serde(default = "default_roll_tolerance")]
+    pub roll_tolerance: Time,
+    /// Hold 松手是否单独判定
+    #[serde(default)]
+    pub tail_mode: TailMode,
+    /// 尾判窗口 = 各档窗口 × 此倍数，松手通常比按下宽松
+    #[serde(default = "default_tail_window_scale")]
+    pub tail_window_scale: f64,
+    /// Hold 上每隔多少拍一个 tick，按住加 combo，松开断 combo；None 关闭
+    #[serde(default)]
+    pub hold_tick: Option<Beat>,
+    /// 按下时在同一轨道里选哪个音符判定
+    #[serde(default)]
+    pub hit_policy: HitPolicy,
+    /// 没有可判音符的按下（空按）如何处理
+    #[serde(default)]
+    pub ghost_tap: GhostTapPolicy,
+}
+
+/// What a press that hits no note does; every policy but `Ignore`
+/// reports it as [`JudgeResult::Empty`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GhostTapPolicy {
+    #[default]
+    Ignore,
+    /// Only counted for the mash stats
+    Count,
+    BreakCombo,
+    /// Takes `amount` (0..=1) off the life gauge
+    DrainGauge { amount: f64 },
+}
+
+/// Which note of a lane a press is judged against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HitPolicy {
+    /// The first unfinished note takes every press, including an active
+    /// roll or a released hold, so it can eat presses meant for the next one
+    #[default]
+    CursorLock,
+    /// The earliest pending note whose window contains the press, passing
+    /// over an active roll that [`HitPolicy::CursorLock`] would refresh
+    EarliestInWindow,
+    /// The pending note closest to the press; skipped notes are missed later
+    Nearest,
+}
+
+/// How the release of a hold is judged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TailMode {
+    /// Only the press counts, releasing near the end is enough
+    #[default]
+    Off,
+    /// The release is judged against `end`, the hold gets the worse of head and tail
+    Combined,
+    /// Head and tail are two judgments, the head reported on press
+    Separate,
+}
+
+fn default_tail_window_scale() -> f64 {
+    1.5
+}
+
+fn default_mine_window() -> Time {
+    Time(0.05)
+}
+
+/// [`JudgeCore::hold_tick`] 最短 1/192 拍
+const MIN_HOLD_TICK_DIVISION: u32 = 192;
+
+fn default_roll_tolerance() -> Time {
+    Time(0.3)
 }
 
 impl JudgeCore {
     pub fn new(window: JudgeWindow, hold_tolerance: Time) -> Self {
-        Self { window, hold_tolerance }
+        Self {
+            window,
+            hold_tolerance,
+            mine_window: default_mine_window(),
+            roll_tolerance: default_roll_tolerance(),
+            tail_mode: TailMode::default(),
+            tail_window_scale: default_tail_window_scale(),
+            hold_tick: None,
+            hit_policy: HitPolicy::default(),
+            ghost_tap: GhostTapPolicy::default(),
+        }
     }
 
-    fn result_from_delta(&self, delta: Time) -> JudgeResult {
-        let abs = delta.abs();
+    /// Checks that serde cannot express, run when the config is loaded
+    pub fn validate(&self) -> anyhow::Result<()> {
+        // 为 0 时 Roll 每次敲击都立刻超时，bot 也无法按间隔连打
+        if !(self.roll_tolerance.0.is_finite() && self.roll_tolerance.0 > 0.0) {
+            bail!("roll_tolerance {} must be a positive number of seconds", self.roll_tolerance.0);
+        }
+        for (name, value) in [
+            ("hold_tolerance", self.hold_tolerance.0),
+            ("mine_window", self.mine_window.0),
+            ("tail_window_scale", self.tail_window_scale),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                bail!("{name} {value} must be a non-negative number");
+            }
+        }
+        // 负数会让空按回血，NaN 会污染血条
+        if let GhostTapPolicy::DrainGauge { amount } = self.ghost_tap
+            && !(0.0..=1.0).contains(&amount)
+        {
+            bail!("DrainGauge amount {amount} must be between 0 and 1");
+        }
+        // 间隔太小时一个 Hold 会展开成海量 tick
+        if let Some(interval) = self.hold_tick
+            && interval < Beat::new(0, 1, MIN_HOLD_TICK_DIVISION)
+        {
+            bail!("hold_tick {interval} must be at least 1/{MIN_HOLD_TICK_DIVISION} beat");
+        }
+        Ok(())
+    }
+
+    /// Tick beats of a hold, strictly between its start and end
+    pub fn hold_ticks(&self, note: &Note) -> Vec<Beat> {
+        let (Some(interval), Note::Hold { start, end, .. }) = (self.hold_tick, note) else {
+            return vec![];
+        };
+        if interval <= Beat::ZERO {
+            return vec![];
+        }
+        std::iter::successors(Some(*start + interval), |&b| Some(b + interval))
+            .take_while(|b| b < end)
+            .collect()
+    }
+
+    /// Judgments a note contributes to the score, 2 for holds in [`TailMode::Separate`]
+    pub fn judgment_count(&self, note: &Note) -> usize {
+        match note {
+            Note::Mine { .. } => 0,
+            Note::Hold { .. } if self.tail_mode == TailMode::Separate => 2,
+            _ => 1,
+        }
+    }
+
+    /// 松手相对 end 的判定，超出尾判窗口返回 None
+    fn tail_result(&self, delta: Time) -> Option<JudgeResult> {
+        self.window.tier_within(delta, self.tail_window_scale)
+            .map(|tier| JudgeResult::Hit { tier, delta })
+    }
 
-        match abs {
-            d if d <= self.window.perfect => JudgeResult::Perfect(delta),
-            d if d <= self.window.good => JudgeResult::Good(delta),
-            _ => JudgeResult::Miss,
+    /// 一直按到尾判窗口结束也没松手，按最宽一档算
+    fn held_through(&self) -> JudgeResult {
+        JudgeResult::Hit {
+            tier: self.window.tiers().len() - 1,
+            delta: Time(self.window.max_late().0 * self.tail_window_scale),
         }
     }
 
+    /// Hold 结束时要报告的结果
+    fn finish_hold(&self, head: JudgeResult, tail: JudgeResult) -> JudgeResult {
+        match self.tail_mode {
+            TailMode::Off => head,
+            TailMode::Separate => tail,
+            TailMode::Combined => match (head, tail) {
+                (JudgeResult::Hit { tier: h, .. }, JudgeResult::Hit { tier: t, .. }) => if t > h { tail } else { head },
+                _ => JudgeResult::Miss,
+            },
+        }
+    }
+
+    fn result_from_delta(&self, delta: Time) -> JudgeResult {
+        match self.window.tier_of(delta) {
+            Some(tier) => JudgeResult::Hit { tier, delta },
+            None => JudgeResult::Miss,
+        }
+    }
+
+    /// 档位外的提前按键：落在 Poor 窗口内时返回 Poor，否则无事发生
+    fn poor(&self, delta: Time) -> Option<JudgeResult> {
+        self.window.is_poor(delta).then_some(JudgeResult::Poor { delta })
+    }
+
+    /// 空按的结果，Ignore 时不报告
+    fn empty(&self) -> Option<JudgeResult> {
+        (self.ghost_tap != GhostTapPolicy::Ignore).then_some(JudgeResult::Empty)
+    }
+
+    /// 窗口外的按下：Poor 或空按
+    fn miss_press(&self, delta: Time) -> Option<JudgeResult> {
+        self.poor(delta).or_else(|| self.empty())
+    }
+
     fn judge(&self, input: JudgeInput) -> JudgeResult {
         let delta = input.input_time - input.note_time;
         self.result_from_delta(delta)
@@ -69,29 +541,84 @@ pub enum NoteState {
     Pending,
     Holding(JudgeResult), // Hold 按下, 记录按下时的判定
     Releasing(JudgeResult, Time), // 防抖
+    Rolling(JudgeResult, Time), // Roll 头已判定，记录上一次敲击时间
     Hit,
     Missed,
+    Exploded, // 地雷被踩
+    Avoided,  // 地雷安全通过
+}
+
+impl NoteState {
+    /// 已经有最终结果，不再参与判定
+    pub fn is_done(&self) -> bool {
+        matches!(self, NoteState::Hit | NoteState::Missed | NoteState::Exploded | NoteState::Avoided)
+    }
 }
 
 /// Invariants:
 /// - notes sorted by judge time (Tap.time / Hold.end)
 /// - states.len() == notes.len()
-/// - cursor points to first Pending note
+/// - cursor points to first note that is not done; notes after it may be
+///   judged early while a mine in front of them is still pending
 pub struct NoteJudge {
     pub id: u8,
     pub notes: Vec<Note>,
     pub states: Vec<NoteState>,
     pub(crate) cursor: usize,
+    /// 每个 Hold 的 tick 时间，建立时算好
+    ticks: Vec<Vec<Time>>,
+    /// 每个 Hold 已结算的 tick 数，即 `ticks` 里的游标
+    ticks_done: Vec<usize>,
+    /// 在 on_input 中产生、等下一次 `JudgeManager::update` 报告的结果
+    queued: Vec<(usize, JudgeResult)>,
+    /// 产生判定时立即记录，由 `JudgeManager::drain_events` 取走
+    events: Vec<JudgeEvent>,
+}
+
+/// 判定结果同时写入返回值和事件队列
+struct Emitter<'a> {
+    track: u8,
+    results: &'a mut Vec<(usize, JudgeResult)>,
+    events: &'a mut Vec<JudgeEvent>,
+}
+
+impl Emitter<'_> {
+    /// 随时间推移产生的判定，没有对应的输入
+    fn push(&mut self, idx: usize, kind: NoteKind, phase: JudgePhase, note_time: Time, result: JudgeResult) {
+        self.results.push((idx, result));
+        self.events.push(JudgeEvent::new(self.track, idx, kind, phase, note_time, result, None));
+    }
+
+    fn ticks(&mut self, idx: usize, times: &[Time], held: bool) {
+        for &time in times {
+            self.push(idx, NoteKind::Hold, JudgePhase::Tick, time, JudgeResult::Tick { held });
+        }
+    }
+}
+
+/// `done` 之后、不晚于 `until` (None 为全部) 的 tick，并把 `done` 推进过去
+fn ticks_due<'a>(ticks: &'a [Time], done: &mut usize, until: Option<Time>) -> &'a [Time] {
+    let rest = &ticks[*done..];
+    let due = until.map_or(rest.len(), |t| rest.partition_point(|&time| time <= t));
+    *done += due;
+    &rest[..due]
 }
 
 impl NoteJudge {
-    fn new(track: Track) -> Self {
+    fn new(track: Track, judge: &JudgeCore, timing_map: &TimingMap) -> Self {
         let states = vec![NoteState::Pending; track.notes.len()];
+        let ticks = track.notes.iter()
+            .map(|n| judge.hold_ticks(n).iter().map(|b| timing_map.beat_to_time(b)).collect())
+            .collect();
         Self {
             id: track.id,
+            ticks,
+            ticks_done: vec![0; track.notes.len()],
             notes: track.notes,
             states,
             cursor: 0,
+            queued: vec![],
+            events: vec![],
         }
     }
 
@@ -103,57 +630,105 @@ impl NoteJudge {
         timing_map: &TimingMap,
     ) -> Vec<(usize, JudgeResult)> {
         let mut results = Vec::new();
+        let mut out = Emitter { track: self.id, results: &mut results, events: &mut self.events };
 
-        while self.cursor < self.notes.len() {
-            let note = &self.notes[self.cursor];
-            let state = self.states[self.cursor];
+        let mut idx = self.cursor;
+        while idx < self.notes.len() {
+            let note = &self.notes[idx];
+            let state = self.states[idx];
+
+            // 被提前判定过的音符直接跳过
+            if state.is_done() {
+                idx += 1;
+                continue;
+            }
 
             match note {
+                Note::Mine { beat } => {
+                    let time = timing_map.beat_to_time(beat);
+                    // 地雷安全通过，不产生判定
+                    if now - time > judge.mine_window {
+                        self.states[idx] = NoteState::Avoided;
+                    }
+                    // 还在窗口内的地雷不挡住后面音符的超时判定，和 on_input 一样跳过
+                    idx += 1;
+                    continue;
+                }
                 Note::Tap { beat, .. } => {
                     let time = timing_map.beat_to_time(beat);
                     // Tap 超时未打 -> Miss
-                    if now - time > judge.window.good {
-                        self.states[self.cursor] = NoteState::Missed;
-                        results.push((self.cursor, JudgeResult::Miss));
-                        self.cursor += 1;
+                    if now - time > judge.window.max_late() {
+                        self.states[idx] = NoteState::Missed;
+                        out.push(idx, NoteKind::Tap, JudgePhase::Head, time, JudgeResult::Miss);
+                        idx += 1;
                         continue;
                     }
                 }
-                Note::Hold { start, end, .. } => {
+                Note::Hold { start, end, .. } | Note::Roll { start, end, .. } => {
                     let start_time = timing_map.beat_to_time(start);
                     let end_time = timing_map.beat_to_time(end);
 
                     match state {
                         NoteState::Pending => {
-                            if now - start_time > judge.window.good {
-                                self.states[self.cursor] = NoteState::Missed;
-                                results.push((self.cursor, JudgeResult::Miss));
-                                self.cursor += 1;
+                            if now - start_time > judge.window.max_late() {
+                                self.states[idx] = NoteState::Missed;
+                                // 头尾分开判时，漏掉的 Hold 两个判定都是 Miss
+                                let phases = [(JudgePhase::Head, start_time), (JudgePhase::Tail, end_time)];
+                                for (phase, time) in phases.into_iter().take(judge.judgment_count(note)) {
+                                    out.push(idx, note.kind(), phase, time, JudgeResult::Miss);
+                                }
+                                let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], None);
+                                out.ticks(idx, ticks, false);
+                                idx += 1;
                                 continue;
                             }
                         }
                         NoteState::Holding(j) => {
-                            if end_time - now < judge.window.good {
-                                self.states[self.cursor] = NoteState::Hit;
-                                results.push((self.cursor, j));
-                                self.cursor += 1;
+                            let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], Some(now));
+                            out.ticks(idx, ticks, true);
+                            let finished = match judge.tail_mode {
+                                TailMode::Off => (end_time - now < judge.window.max_early()).then_some(j),
+                                _ => (now - end_time > Time(judge.window.max_late().0 * judge.tail_window_scale))
+                                    .then(|| judge.finish_hold(j, judge.held_through())),
+                            };
+                            if let Some(result) = finished {
+                                self.states[idx] = NoteState::Hit;
+                                // 提前完成的 Hold 剩余 tick 视为按住
+                                let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], None);
+                                out.ticks(idx, ticks, true);
+                                out.push(idx, note.kind(), JudgePhase::Tail, end_time, result);
+                                idx += 1;
                                 continue;
                             }
                             break; // 还在 Holding 期间
                         }
-                        NoteState::Releasing(j, release_time) => {
-                            // 如果距离结束很近，直接判 Hit
-                            if end_time - now < judge.window.good {
-                                self.states[self.cursor] = NoteState::Hit;
-                                results.push((self.cursor, j));
-                                self.cursor += 1;
+                        // Hold 松手后的防抖和 Roll 两次敲击的间隔共用一套容错逻辑
+                        NoteState::Releasing(j, since) | NoteState::Rolling(j, since) => {
+                            // 松开期间经过的 tick 断 combo (Roll 没有 tick)
+                            let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], Some(now));
+                            out.ticks(idx, ticks, false);
+                            // 如果距离结束很近，直接判 Hit；有尾判时提前松手只能靠重新按下挽回
+                            let tail_judged = matches!(state, NoteState::Releasing(..)) && judge.tail_mode != TailMode::Off;
+                            if !tail_judged && end_time - now < judge.window.max_early() {
+                                self.states[idx] = NoteState::Hit;
+                                let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], None);
+                                out.ticks(idx, ticks, true);
+                                out.push(idx, note.kind(), JudgePhase::Tail, end_time, j);
+                                idx += 1;
                                 continue;
                             }
-                            // 如果松手时间超过了容错值，判定为 Missed
-                            if now - release_time > judge.hold_tolerance {
-                                self.states[self.cursor] = NoteState::Missed;
-                                results.push((self.cursor, JudgeResult::Miss));
-                                self.cursor += 1;
+                            let tolerance = if matches!(state, NoteState::Rolling(..)) {
+                                judge.roll_tolerance
+                            } else {
+                                judge.hold_tolerance
+                            };
+                            // 如果松手 (或停止连打) 超过了容错值，判定为 Missed
+                            if now - since > tolerance {
+                                self.states[idx] = NoteState::Missed;
+                                out.push(idx, note.kind(), JudgePhase::Tail, end_time, JudgeResult::Miss);
+                                let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], None);
+                                out.ticks(idx, ticks, false);
+                                idx += 1;
                                 continue;
                             }
                             break; // 还在容错观察期内
@@ -164,6 +739,10 @@ impl NoteJudge {
             }
             break;
         }
+        // 游标只越过已判定的音符，还在窗口内的地雷留给 on_input
+        while self.cursor < self.notes.len() && self.states[self.cursor].is_done() {
+            self.cursor += 1;
+        }
         results
     }
 
@@ -175,16 +754,57 @@ impl NoteJudge {
         judge: &JudgeCore,
         timing_map: &TimingMap,
     ) -> Option<JudgeResult> {
-        // 清理过期 note
-        self.update(input_time, judge, timing_map);
+        // 清理过期 note，结果留给下一次 JudgeManager::update
+        let expired = self.update(input_time, judge, timing_map);
+        self.queued.extend(expired);
 
-        if self.cursor >= self.notes.len() {
-            return None;
+        // 地雷不阻塞游标：按下时先检查窗口内的地雷，再越过地雷找到要判定的音符
+        let mut idx = self.cursor;
+        while let Some(note) = self.notes.get(idx) {
+            if let Note::Mine { beat } = note
+                && is_down
+                && self.states[idx] == NoteState::Pending
+                && (input_time - timing_map.beat_to_time(beat)).abs() <= judge.mine_window
+            {
+                let time = timing_map.beat_to_time(beat);
+                self.events.push(JudgeEvent::new(self.id, idx, NoteKind::Mine, JudgePhase::Head, time, JudgeResult::MineHit, Some(input_time)));
+                self.states[idx] = NoteState::Exploded;
+                self.advance_cursor();
+                return Some(JudgeResult::MineHit);
+            }
+            if !matches!(note, Note::Mine { .. }) && !self.states[idx].is_done() {
+                break;
+            }
+            idx += 1;
+        }
+
+        if idx >= self.notes.len() {
+            if !is_down {
+                return None;
+            }
+            let result = judge.empty();
+            if result.is_some() {
+                self.events.push(JudgeEvent::empty(self.id, input_time));
+            }
+            return result;
+        }
+        // 松手只属于正在按住的 Hold；容错期内重新按下也回到那个 Hold，不另找音符
+        let held = (idx..self.notes.len())
+            .find(|&i| matches!(self.states[i], NoteState::Holding(_) | NoteState::Releasing(..)));
+        match held {
+            Some(h) if !is_down || matches!(self.states[h], NoteState::Releasing(..)) => idx = h,
+            _ if is_down => {
+                if let Some(target) = self.hit_target(idx, input_time, judge, timing_map) {
+                    idx = target;
+                }
+            }
+            _ => {}
         }
 
-        let note = &self.notes[self.cursor];
+        let note = &self.notes[idx];
 
         match note {
+            Note::Mine { .. } => None,
             // ================= TAP =================
             Note::Tap { beat, .. } => {
                 if !is_down {
@@ -193,49 +813,192 @@ impl NoteJudge {
 
                 let time = timing_map.beat_to_time(beat);
                 let delta = input_time - time;
-                if delta.abs() <= judge.window.good {
+                if judge.window.contains(delta) {
                     let result = judge.judge(JudgeInput {
                         note_time: time,
                         input_time,
                     });
-                    self.states[self.cursor] = if let JudgeResult::Miss = result {
+                    self.states[idx] = if let JudgeResult::Miss = result {
                         NoteState::Missed
                     } else {
                         NoteState::Hit
                     };
-                    self.cursor += 1;
+                    self.events.push(JudgeEvent::new(self.id, idx, NoteKind::Tap, JudgePhase::Head, time, result, Some(input_time)));
+                    self.advance_cursor();
                     return Some(result);
                 }
-                None
+                self.miss_press(idx, time, input_time, judge)
             }
-            Note::Hold { start, .. } => {
-                match self.states[self.cursor] {
+            Note::Hold { start, end, .. } => {
+                match self.states[idx] {
                     NoteState::Pending => {
                         if !is_down { return None; }
                         let start_time = timing_map.beat_to_time(start);
-                        if (input_time - start_time).abs() <= judge.window.good {
-                            let result = judge.judge(JudgeInput { note_time: start_time, input_time });
-                            self.states[self.cursor] = NoteState::Holding(result);
+                        let delta = input_time - start_time;
+                        if !judge.window.contains(delta) {
+                            return self.miss_press(idx, start_time, input_time, judge);
                         }
-                        None
+                        let result = judge.judge(JudgeInput { note_time: start_time, input_time });
+                        self.states[idx] = NoteState::Holding(result);
+                        // 头尾分开判时头判立即报告
+                        if judge.tail_mode != TailMode::Separate {
+                            return None;
+                        }
+                        self.events.push(JudgeEvent::new(self.id, idx, NoteKind::Hold, JudgePhase::Head, start_time, result, Some(input_time)));
+                        Some(result)
                     }
                     NoteState::Holding(res) => {
                         if is_down { return None; }
+                        // 尾判窗口内松手即完成
+                        let end_time = timing_map.beat_to_time(end);
+                        if judge.tail_mode != TailMode::Off
+                            && let Some(tail) = judge.tail_result(input_time - end_time)
+                        {
+                            self.states[idx] = NoteState::Hit;
+                            let ticks = ticks_due(&self.ticks[idx], &mut self.ticks_done[idx], None);
+                            Emitter { track: self.id, results: &mut self.queued, events: &mut self.events }.ticks(idx, ticks, true);
+                            let result = judge.finish_hold(res, tail);
+                            self.events.push(JudgeEvent::new(self.id, idx, NoteKind::Hold, JudgePhase::Tail, end_time, result, Some(input_time)));
+                            self.advance_cursor();
+                            return Some(result);
+                        }
                         // 进入观察期
-                        self.states[self.cursor] = NoteState::Releasing(res, input_time);
+                        self.states[idx] = NoteState::Releasing(res, input_time);
                         None
                     }
                     NoteState::Releasing(res, _) => {
                         if !is_down { return None; }
                         // 容错期内重新按下，恢复 Holding
-                        self.states[self.cursor] = NoteState::Holding(res);
+                        self.states[idx] = NoteState::Holding(res);
                         None
                     }
                     _ => None,
                 }
             }
+            Note::Roll { start, .. } => {
+                // Roll 只看按下，松手无影响
+                if !is_down { return None; }
+                match self.states[idx] {
+                    NoteState::Pending => {
+                        let start_time = timing_map.beat_to_time(start);
+                        let delta = input_time - start_time;
+                        if !judge.window.contains(delta) {
+                            return self.miss_press(idx, start_time, input_time, judge);
+                        }
+                        let result = judge.judge(JudgeInput { note_time: start_time, input_time });
+                        self.states[idx] = NoteState::Rolling(result, input_time);
+                    }
+                    NoteState::Rolling(res, _) => {
+                        self.states[idx] = NoteState::Rolling(res, input_time);
+                    }
+                    _ => {}
+                }
+                None
+            }
+        }
+    }
+    /// 按下落在 `idx` 音符的窗口外：Poor 或空按
+    fn miss_press(&mut self, idx: usize, note_time: Time, input_time: Time, judge: &JudgeCore) -> Option<JudgeResult> {
+        let result = judge.miss_press(input_time - note_time)?;
+        let kind = self.notes[idx].kind();
+        self.events.push(JudgeEvent::miss_press(self.id, idx, kind, note_time, result, input_time));
+        Some(result)
+    }
+
+    /// 按 [`HitPolicy`] 从 `first` 起挑选按下要判定的音符，None 表示就判 `first`
+    fn hit_target(&self, first: usize, input_time: Time, judge: &JudgeCore, timing_map: &TimingMap) -> Option<usize> {
+        if judge.hit_policy == HitPolicy::CursorLock {
+            return None;
+        }
+        let mut best: Option<(usize, f64)> = None;
+        for i in first..self.notes.len() {
+            let note = &self.notes[i];
+            if note.is_mine() || self.states[i] != NoteState::Pending {
+                continue;
+            }
+            let delta = input_time - timing_map.beat_to_time(&note.beat());
+            // 之后的音符只会更晚
+            if delta.0 < -judge.window.max_early().0 {
+                break;
+            }
+            if !judge.window.contains(delta) {
+                continue;
+            }
+            match judge.hit_policy {
+                HitPolicy::EarliestInWindow => return Some(i),
+                _ if best.is_none_or(|(_, d)| delta.0.abs() < d) => best = Some((i, delta.0.abs())),
+                _ => {}
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    fn advance_cursor(&mut self) {
+        while self.cursor < self.notes.len() && self.states[self.cursor].is_done() {
+            self.cursor += 1;
         }
     }
+
+    /// 越过地雷和已判定的音符，下一个需要击打的音符
+    pub fn next_playable(&self) -> Option<usize> {
+        (self.cursor..self.notes.len())
+            .find(|&i| !self.notes[i].is_mine() && !self.states[i].is_done())
+    }
+
+    /// 还未结算的 hold tick 数
+    pub fn unresolved_ticks(&self) -> usize {
+        (self.cursor..self.notes.len())
+            .map(|i| self.ticks[i].len() - self.ticks_done[i])
+            .sum()
+    }
+
+    /// 剩下的音符全部判 Miss (地雷视为通过)，剩余 tick 判断，游标推到最后
+    fn finish(&mut self, judge: &JudgeCore, timing_map: &TimingMap) {
+        // 结束时没有后续的 update，只需要事件
+        let mut results = Vec::new();
+        let mut out = Emitter { track: self.id, results: &mut results, events: &mut self.events };
+
+        for i in self.cursor..self.notes.len() {
+            let note = &self.notes[i];
+            let state = self.states[i];
+            if state.is_done() {
+                continue;
+            }
+            if note.is_mine() {
+                self.states[i] = NoteState::Avoided;
+                continue;
+            }
+            let start_time = timing_map.beat_to_time(&note.beat());
+            let end_time = timing_map.beat_to_time(&note.end_beat());
+            let misses: Vec<_> = match state {
+                NoteState::Pending => [(JudgePhase::Head, start_time), (JudgePhase::Tail, end_time)]
+                    .into_iter()
+                    .take(judge.judgment_count(note))
+                    .collect(),
+                // 头判已经单独报告或并入尾判，只剩尾判
+                _ => vec![(JudgePhase::Tail, end_time)],
+            };
+            for (phase, time) in misses {
+                out.push(i, note.kind(), phase, time, JudgeResult::Miss);
+            }
+            let ticks = ticks_due(&self.ticks[i], &mut self.ticks_done[i], None);
+            out.ticks(i, ticks, false);
+            self.states[i] = NoteState::Missed;
+        }
+        self.cursor = self.notes.len();
+    }
+
+    /// 还未报告的计分判定数 (不含地雷)
+    pub fn unjudged_count(&self, judge: &JudgeCore) -> usize {
+        (self.cursor..self.notes.len())
+            .map(|i| match self.states[i] {
+                state if state.is_done() => 0,
+                // 头判已经报告，只剩尾判
+                NoteState::Holding(_) | NoteState::Releasing(..) if judge.tail_mode == TailMode::Separate => 1,
+                _ => judge.judgment_count(&self.notes[i]),
+            })
+            .sum()
+    }
 }
 
 pub struct JudgeManager {
@@ -253,7 +1016,7 @@ impl JudgeManager {
     pub fn new(tracks: Vec<Track>, timing_map: TimingMap, core: JudgeCore) -> Self {
         let mut result = vec![];
         for track in tracks {
-            result.push(NoteJudge::new(track))
+            result.push(NoteJudge::new(track, &core, &timing_map))
         }
         Self {
             judges: result,
@@ -272,7 +1035,8 @@ impl JudgeManager {
         let mut all_results = Vec::new();
         for judge in self.judges.iter_mut() {
             let real_id = judge.id as usize; // 获取 NoteJudge 内部存储的真实 id
-            let results = judge.update(now, &self.core, &self.map);
+            let mut results = std::mem::take(&mut judge.queued);
+            results.extend(judge.update(now, &self.core, &self.map));
             for (note_idx, res) in results {
                 all_results.push(UpdateResult {
                     track_idx: real_id, // 🚩 存储真实 ID
@@ -284,24 +1048,20 @@ impl JudgeManager {
         all_results
     }
 
-    pub fn clear_and_count_unjudged(&mut self) -> u32 {
-        let mut total_unjudged = 0;
+    /// Every judgment since the last call, per track in the order they happened.
+    /// Covers both [`Self::on_input`] and [`Self::update`], including results
+    /// they return later or not at all (e.g. hold ticks queued on release)
+    pub fn drain_events(&mut self) -> Vec<JudgeEvent> {
+        self.judges.iter_mut()
+            .flat_map(|nj| std::mem::take(&mut nj.events))
+            .collect()
+    }
 
+    /// 歌曲结束：未判定的音符和剩余 tick 全部判断，结果从 [`Self::drain_events`] 取走
+    pub fn finish(&mut self) {
         for nj in &mut self.judges {
-            // 1. 计算当前轨道还没判定的音符数量
-            let remaining = nj.notes.len().saturating_sub(nj.cursor);
-            total_unjudged += remaining as u32;
-
-            // 2. 将这些音符的状态全部强转为 Missed (防止 UI 渲染出错)
-            for i in nj.cursor..nj.notes.len() {
-                nj.states[i] = NoteState::Missed;
-            }
-
-            // 3. 将游标推到最后，标记该轨道已清空
-            nj.cursor = nj.notes.len();
+            nj.finish(&self.core, &self.map);
         }
-
-        total_unjudged
     }
 }
 
@@ -309,27 +1069,22 @@ impl JudgeManager {
 mod tests {
     use super::*;
     use crate::core::chart::Note;
-    use crate::core::judge::JudgeResult::Perfect;
     use crate::core::timing::{Beat, BpmChange};
 
     // 辅助函数：快速创建测试环境
     fn setup_test(notes: Vec<Note>) -> (NoteJudge, JudgeCore, TimingMap) {
-        let core = JudgeCore::new(JudgeWindow {
-            perfect: Time(0.03),
-            good: Time(0.08),
-        }, Time(0.008));
-        let map = TimingMap {
-            offset: Time(0.0),
-            bpm_changes: vec![BpmChange{beat: Beat(0.0), bpm: 60.0}],
-        };
-        (NoteJudge::new(Track { id: 0, notes }), core, map)
+        let core = JudgeCore::new(JudgeWindow::classic(Time(0.03), Time(0.08)), Time(0.008));
+        let map = TimingMap::new(Time(0.0), vec![BpmChange{beat: Beat::from(0.0), bpm: 60.0}]).unwrap();
+        let nj = NoteJudge::new(Track { id: 0, notes }, &core, &map);
+        (nj, core, map)
     }
 
     #[test]
     fn test_hold_miss_at_start() {
         let notes = vec![Note::Hold {
-            start: Beat(1.0),
-            end: Beat(2.0),
+            start: Beat::from(1.0),
+            end: Beat::from(2.0),
+            sound: None,
         }];
         let (mut nj, core, map) = setup_test(notes);
 
@@ -347,8 +1102,9 @@ mod tests {
     #[test]
     fn test_hold_success_flow() {
         let notes = vec![Note::Hold {
-            start: Beat(1.0),
-            end: Beat(2.0),
+            start: Beat::from(1.0),
+            end: Beat::from(2.0),
+            sound: None,
         }];
         let (mut nj, core, map) = setup_test(notes);
 
@@ -360,7 +1116,7 @@ mod tests {
         let result = nj.update(Time(2.2), &core, &map);
         assert!(!result.is_empty());
 
-        if let Perfect(d) = result[0].1 {
+        if let JudgeResult::Hit { tier: 0, delta: d } = result[0].1 {
             assert!((d.0 - 0.01).abs() < 1e-6);
         } else {
             panic!()
@@ -372,8 +1128,9 @@ mod tests {
     #[test]
     fn test_hold_release_with_delta_early() {
         let notes = vec![Note::Hold {
-            start: Beat(1.0),
-            end: Beat(2.0),
+            start: Beat::from(1.0),
+            end: Beat::from(2.0),
+            sound: None,
         }];
 
         let (mut nj, core, map) = setup_test(notes);
@@ -385,7 +1142,7 @@ mod tests {
         // update
         assert!(!result.is_empty());
 
-        if let Perfect(d) = result[0].1 {
+        if let JudgeResult::Hit { tier: 0, delta: d } = result[0].1 {
             assert!((d.0 - 0.01).abs() < 1e-6);
         } else {
             panic!()
@@ -397,8 +1154,9 @@ mod tests {
     #[test]
     fn test_hold_early_release_miss() {
         let notes = vec![Note::Hold {
-            start: Beat(1.0),
-            end: Beat(2.0),
+            start: Beat::from(1.0),
+            end: Beat::from(2.0),
+            sound: None,
         }];
         let (mut nj, core, map) = setup_test(notes);
 
@@ -416,8 +1174,9 @@ mod tests {
     #[test]
     fn test_hold_infinite_press_no_miss() {
         let notes = vec![Note::Hold {
-            start: Beat(1.0),
-            end: Beat(2.0),
+            start: Beat::from(1.0),
+            end: Beat::from(2.0),
+            sound: None,
         }];
         let (mut nj, core, map) = setup_test(notes);
 
@@ -428,4 +1187,467 @@ mod tests {
         assert_eq!(nj.states[0], NoteState::Hit);
         assert_eq!(nj.cursor, 1); // Note 被击打，不卡游标
     }
+
+    #[test]
+    fn test_mine_pressed_explodes() {
+        let notes = vec![Note::Mine { beat: Beat::from(1.0) }];
+        let (mut nj, core, map) = setup_test(notes);
+
+        // 窗口外按下无事发生
+        assert_eq!(nj.on_input(Time(0.9), true, &core, &map), None);
+        assert_eq!(nj.states[0], NoteState::Pending);
+
+        assert_eq!(nj.on_input(Time(1.02), true, &core, &map), Some(JudgeResult::MineHit));
+        assert_eq!(nj.states[0], NoteState::Exploded);
+        assert_eq!(nj.cursor, 1);
+    }
+
+    #[test]
+    fn test_mine_passed_without_result() {
+        let notes = vec![Note::Mine { beat: Beat::from(1.0) }, Note::Tap { beat: Beat::from(2.0), sound: None }];
+        let (mut nj, core, map) = setup_test(notes);
+
+        let results = nj.update(Time(1.1), &core, &map);
+        assert!(results.is_empty());
+        assert_eq!(nj.states[0], NoteState::Avoided);
+        assert_eq!(nj.cursor, 1);
+        assert_eq!(nj.unjudged_count(&core), 1);
+    }
+
+    #[test]
+    fn test_mine_does_not_block_next_note() {
+        // 地雷之后紧跟一个 Tap，提前按 Tap 时地雷还没过去
+        let notes = vec![Note::Mine { beat: Beat::from(1.0) }, Note::Tap { beat: Beat::from(1.01), sound: None }];
+        let (mut nj, core, map) = setup_test(notes);
+
+        let result = nj.on_input(Time(0.94), true, &core, &map);
+        assert!(matches!(result, Some(JudgeResult::Hit { tier: 1, .. })));
+        assert_eq!(nj.states, vec![NoteState::Pending, NoteState::Hit]);
+        assert_eq!(nj.cursor, 0);
+
+        nj.update(Time(1.2), &core, &map);
+        assert_eq!(nj.states[0], NoteState::Avoided);
+        assert_eq!(nj.cursor, 2);
+    }
+
+    #[test]
+    fn test_pending_mine_does_not_delay_misses() {
+        let notes = vec![
+            Note::Mine { beat: Beat::from(1.0) },
+            Note::Tap { beat: Beat::from(1.1), sound: None },
+            Note::Tap { beat: Beat::from(1.2), sound: None },
+        ];
+        let (mut nj, mut core, map) = setup_test(notes);
+        core.mine_window = Time(0.5);
+
+        // 地雷窗口还没过，后面的 Tap 照样按时 Miss
+        let results = nj.update(Time(1.25), &core, &map);
+        assert_eq!(results, vec![(1, JudgeResult::Miss)]);
+        let results = nj.update(Time(1.35), &core, &map);
+        assert_eq!(results, vec![(2, JudgeResult::Miss)]);
+        assert_eq!(nj.states[0], NoteState::Pending);
+        assert_eq!(nj.cursor, 0);
+
+        // 地雷仍可踩中
+        assert_eq!(nj.on_input(Time(1.4), true, &core, &map), Some(JudgeResult::MineHit));
+        assert_eq!(nj.cursor, 3);
+    }
+
+    #[test]
+    fn test_roll_kept_alive_by_tapping() {
+        let notes = vec![Note::Roll { start: Beat::from(1.0), end: Beat::from(2.0), sound: None }];
+        let (mut nj, core, map) = setup_test(notes);
+
+        nj.on_input(Time(1.0), true, &core, &map);
+        assert!(matches!(nj.states[0], NoteState::Rolling(_, _)));
+
+        // 松手不影响 Roll，只要间隔不超过 roll_tolerance 持续敲击
+        let mut t = 1.0;
+        while t < 1.7 {
+            nj.on_input(Time(t + 0.01), false, &core, &map);
+            t += 0.2;
+            nj.on_input(Time(t), true, &core, &map);
+        }
+
+        let result = nj.update(Time(1.95), &core, &map);
+        assert!(matches!(result[..], [(0, JudgeResult::Hit { tier: 0, .. })]));
+        assert_eq!(nj.states[0], NoteState::Hit);
+        assert_eq!(nj.cursor, 1);
+    }
+
+    #[test]
+    fn test_roll_missed_when_tapping_stops() {
+        let notes = vec![Note::Roll { start: Beat::from(1.0), end: Beat::from(3.0), sound: None }];
+        let (mut nj, core, map) = setup_test(notes);
+
+        nj.on_input(Time(1.0), true, &core, &map);
+        // 一直按住也不算，必须重新敲击
+        let result = nj.update(Time(1.0) + core.roll_tolerance + Time(0.01), &core, &map);
+        assert!(matches!(result[..], [(0, JudgeResult::Miss)]));
+        assert_eq!(nj.states[0], NoteState::Missed);
+    }
+
+    #[test]
+    fn test_tiers_pick_tightest_window() {
+        let core = JudgeCore::new(JudgePreset::StepMania.into(), Time(0.008));
+        let tier = |d: f64| match core.result_from_delta(Time(d)) {
+            JudgeResult::Hit { tier, .. } => Some(core.window.tiers()[tier].name.as_str()),
+            _ => None,
+        };
+        assert_eq!(tier(0.01), Some("MARVELOUS"));
+        assert_eq!(tier(-0.05), Some("GREAT"));
+        assert_eq!(tier(0.17), Some("BOO"));
+        assert_eq!(tier(-0.2), None);
+        assert_eq!(core.window.max_late(), Time(0.18));
+    }
+
+    #[test]
+    fn test_window_config_forms() {
+        let preset: JudgeWindow = serde_json::from_str(r#""osu""#).unwrap();
+        assert_eq!(preset.tiers().len(), 5);
+
+        let classic: JudgeWindow = serde_json::from_str(r#"{"perfect": 0.08, "good": 0.16}"#).unwrap();
+        assert_eq!(classic, JudgePreset::Classic.into());
+
+        // 写出的总是完整的 tiers，读回不变
+        let json = serde_json::to_string(&preset).unwrap();
+        assert_eq!(serde_json::from_str::<JudgeWindow>(&json).unwrap(), preset);
+
+        let narrowing = r#"{"tiers": [
+            {"name": "A", "window": 0.05, "weight": 1.0, "combo": "Continue", "color": "white"},
+            {"name": "B", "window": 0.02, "weight": 0.5, "combo": "Break", "color": "gray"}
+        ]}"#;
+        assert!(serde_json::from_str::<JudgeWindow>(narrowing).is_err());
+        assert!(serde_json::from_str::<JudgeWindow>(r#"{"tiers": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_asymmetric_window_and_late_auto_miss() {
+        let tiers = vec![
+            JudgeTier { early: Time(0.02), late: Time(0.04), ..JudgeTier::new("A", 0.0, 1.0, ComboEffect::Continue, "white") },
+            JudgeTier { early: Time(0.05), late: Time(0.10), ..JudgeTier::new("B", 0.0, 0.5, ComboEffect::Continue, "gray") },
+        ];
+        let core = JudgeCore::new(JudgeWindow::new(tiers, None).unwrap(), Time(0.008));
+        assert_eq!(core.result_from_delta(Time(0.03)), JudgeResult::Hit { tier: 0, delta: Time(0.03) });
+        assert_eq!(core.result_from_delta(Time(-0.03)), JudgeResult::Hit { tier: 1, delta: Time(-0.03) });
+        assert_eq!(core.result_from_delta(Time(-0.08)), JudgeResult::Miss);
+
+        let map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 60.0 }]).unwrap();
+        let mut nj = NoteJudge::new(Track { id: 0, notes: vec![Note::Tap { beat: Beat::from(1.0), sound: None }] }, &core, &map);
+        // 早于 early 边界的按键不碰音符
+        assert_eq!(nj.on_input(Time(0.93), true, &core, &map), None);
+        // 晚判窗口比早判宽，1.08 仍未 Miss
+        assert!(nj.update(Time(1.08), &core, &map).is_empty());
+        assert!(matches!(nj.update(Time(1.11), &core, &map)[..], [(0, JudgeResult::Miss)]));
+    }
+
+    #[test]
+    fn test_poor_does_not_consume_note() {
+        let window = JudgeWindow::new(JudgeWindow::classic(Time(0.03), Time(0.08)).tiers, Some(Time(0.2))).unwrap();
+        let core = JudgeCore::new(window, Time(0.008));
+        let map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 60.0 }]).unwrap();
+        let mut nj = NoteJudge::new(Track { id: 0, notes: vec![Note::Tap { beat: Beat::from(1.0), sound: None }] }, &core, &map);
+
+        assert_eq!(nj.on_input(Time(0.7), true, &core, &map), None);
+        assert!(matches!(nj.on_input(Time(0.85), true, &core, &map), Some(JudgeResult::Poor { .. })));
+        assert_eq!(nj.states[0], NoteState::Pending);
+        assert!(matches!(nj.on_input(Time(1.0), true, &core, &map), Some(JudgeResult::Hit { tier: 0, .. })));
+
+        // Poor 窗口不能比档位窄
+        assert!(JudgeWindow::new(core.window.tiers.clone(), Some(Time(0.05))).is_err());
+        let tier: JudgeTier = serde_json::from_str(r#"{"name": "X", "early": 0.02, "late": 0.05, "weight": 1.0, "combo": "Continue", "color": "red"}"#).unwrap();
+        assert_eq!((tier.early, tier.late), (Time(0.02), Time(0.05)));
+    }
+
+    fn hold_with_tail(mode: TailMode) -> (NoteJudge, JudgeCore, TimingMap) {
+        let (nj, mut core, map) = setup_test(vec![Note::Hold { start: Beat::from(1.0), end: Beat::from(2.0), sound: None }]);
+        core.tail_mode = mode;
+        (nj, core, map)
+    }
+
+    #[test]
+    fn test_tail_combined_takes_worse() {
+        let (mut nj, core, map) = hold_with_tail(TailMode::Combined);
+        assert_eq!(nj.on_input(Time(1.0), true, &core, &map), None);
+        // 没有尾判时 1.95 就会自动判 Hit
+        assert!(nj.update(Time(1.95), &core, &map).is_empty());
+        // 尾判窗口 GOOD 0.08 × 1.5
+        let result = nj.on_input(Time(2.1), false, &core, &map);
+        assert!(matches!(result, Some(JudgeResult::Hit { tier: 1, .. })));
+        assert_eq!(nj.states[0], NoteState::Hit);
+        assert_eq!(nj.cursor, 1);
+    }
+
+    #[test]
+    fn test_tail_separate_reports_twice() {
+        let (mut nj, core, map) = hold_with_tail(TailMode::Separate);
+        assert_eq!(nj.unjudged_count(&core), 2);
+        assert!(matches!(nj.on_input(Time(1.0), true, &core, &map), Some(JudgeResult::Hit { tier: 0, .. })));
+        assert_eq!(nj.unjudged_count(&core), 1);
+
+        // 过早松手，容错期过后尾判 Miss
+        assert_eq!(nj.on_input(Time(1.5), false, &core, &map), None);
+        assert!(matches!(nj.update(Time(1.6), &core, &map)[..], [(0, JudgeResult::Miss)]));
+        assert_eq!(nj.unjudged_count(&core), 0);
+
+        // 整个 Hold 漏掉则两个 Miss
+        let (mut nj, core, map) = hold_with_tail(TailMode::Separate);
+        assert_eq!(nj.update(Time(1.2), &core, &map).len(), 2);
+    }
+
+    #[test]
+    fn test_tail_held_through_end() {
+        let (mut nj, core, map) = hold_with_tail(TailMode::Separate);
+        nj.on_input(Time(1.0), true, &core, &map);
+        assert!(nj.update(Time(2.1), &core, &map).is_empty());
+        let result = nj.update(Time(2.2), &core, &map);
+        assert!(matches!(result[..], [(0, JudgeResult::Hit { tier: 1, .. })]));
+    }
+
+    #[test]
+    fn test_hold_ticks_while_held_and_released() {
+        let notes = vec![Note::Hold { start: Beat::from(1.0), end: Beat::from(3.0), sound: None }];
+        let (_, mut core, map) = setup_test(vec![]);
+        core.hold_tick = Some(Beat::new(0, 1, 2));
+        let mut nj = NoteJudge::new(Track { id: 0, notes }, &core, &map);
+        assert_eq!(core.hold_ticks(&nj.notes[0]).len(), 3); // 1.5 2.0 2.5
+        assert_eq!(nj.unresolved_ticks(), 3);
+
+        nj.on_input(Time(1.0), true, &core, &map);
+        assert!(matches!(nj.update(Time(1.6), &core, &map)[..], [(0, JudgeResult::Tick { held: true })]));
+
+        // 松开后经过的 tick 断 combo，整个 Hold Miss 时剩余 tick 一并结算
+        nj.on_input(Time(1.9), false, &core, &map);
+        let results = nj.update(Time(2.0), &core, &map);
+        assert_eq!(results, vec![
+            (0, JudgeResult::Tick { held: false }),
+            (0, JudgeResult::Miss),
+            (0, JudgeResult::Tick { held: false }),
+        ]);
+        assert_eq!(nj.unresolved_ticks(), 0);
+    }
+
+    #[test]
+    fn test_hold_ticks_reported_by_manager() {
+        let track = Track { id: 0, notes: vec![Note::Hold { start: Beat::from(1.0), end: Beat::from(2.0), sound: None }] };
+        let (_, mut core, map) = setup_test(vec![]);
+        core.hold_tick = Some(Beat::new(0, 1, 4));
+        core.tail_mode = TailMode::Combined;
+        let mut manager = JudgeManager::new(vec![track], map, core);
+
+        manager.on_input(0, Time(1.0), true);
+        // 尾判窗口内提前松手，剩余 tick 按住结算，留给 update 报告
+        assert!(matches!(manager.on_input(0, Time(1.95), false), Some(JudgeResult::Hit { .. })));
+        let ticks = manager.update(Time(1.96)).iter()
+            .filter(|u| u.result == JudgeResult::Tick { held: true })
+            .count();
+        assert_eq!(ticks, 3);
+    }
+
+    /// 100ms 间隔的纵连，GOOD 窗口 80ms 让相邻音符窗口重叠
+    fn jack(policy: HitPolicy) -> (NoteJudge, JudgeCore, TimingMap) {
+        let notes = (0..3).map(|i| Note::Tap { beat: Beat::new(1, i, 10), sound: None }).collect();
+        let (nj, mut core, map) = setup_test(notes);
+        core.hit_policy = policy;
+        (nj, core, map)
+    }
+
+    #[test]
+    fn test_jack_cursor_lock_eats_early_press() {
+        for policy in [HitPolicy::CursorLock, HitPolicy::EarliestInWindow] {
+            let (mut nj, core, map) = jack(policy);
+            // 1.075 离第二个音符更近，但第一个还在窗口内，被它吃掉
+            let result = nj.on_input(Time(1.075), true, &core, &map);
+            assert!(matches!(result, Some(JudgeResult::Hit { tier: 1, .. })), "{policy:?}");
+            assert_eq!(nj.states, vec![NoteState::Hit, NoteState::Pending, NoteState::Pending]);
+        }
+    }
+
+    #[test]
+    fn test_jack_nearest_skips_ahead() {
+        let (mut nj, core, map) = jack(HitPolicy::Nearest);
+        let result = nj.on_input(Time(1.075), true, &core, &map);
+        assert!(matches!(result, Some(JudgeResult::Hit { tier: 0, .. })));
+        assert_eq!(nj.states, vec![NoteState::Pending, NoteState::Hit, NoteState::Pending]);
+        assert_eq!(nj.cursor, 0);
+
+        // 被跳过的音符过窗后 Miss，游标越过已判定的音符
+        assert!(matches!(nj.update(Time(1.09), &core, &map)[..], [(0, JudgeResult::Miss)]));
+        assert_eq!(nj.cursor, 2);
+    }
+
+    #[test]
+    fn test_roll_end_jack() {
+        // Roll 结尾紧跟两个 Tap；窗口取二进制精确值，1.375 时 Roll 还没结束而第一个 Tap 刚进窗口
+        let notes = vec![
+            Note::Roll { start: Beat::from(1.0), end: Beat::from(1.5), sound: None },
+            Note::Tap { beat: Beat::from(1.5), sound: None },
+            Note::Tap { beat: Beat::from(1.625), sound: None },
+        ];
+        for (policy, hit) in [
+            (HitPolicy::CursorLock, None),
+            (HitPolicy::EarliestInWindow, Some(1)),
+            (HitPolicy::Nearest, Some(1)),
+        ] {
+            let (mut nj, mut core, map) = setup_test(notes.clone());
+            core.window = JudgeWindow::classic(Time(0.0625), Time(0.125));
+            core.hit_policy = policy;
+            nj.on_input(Time(1.0), true, &core, &map);
+            nj.on_input(Time(1.25), true, &core, &map);
+            let result = nj.on_input(Time(1.375), true, &core, &map);
+
+            // CursorLock 把这次按下交给 Roll 续命，其余判给进窗的 Tap
+            assert!(matches!(nj.states[0], NoteState::Rolling(_, t) if t == Time(if hit.is_none() { 1.375 } else { 1.25 })), "{policy:?}");
+            let hits: Vec<usize> = (1..3).filter(|&i| nj.states[i] == NoteState::Hit).collect();
+            assert_eq!(hits, hit.into_iter().collect::<Vec<_>>(), "{policy:?}");
+            assert_eq!(result.is_some(), hit.is_some(), "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_repress_restores_released_hold() {
+        // 尾判 + 较长的松手容错：Hold 松开后还在观察期，下一个音符已进入窗口，重新按下仍回到 Hold
+        let notes = vec![
+            Note::Hold { start: Beat::from(1.0), end: Beat::from(2.0), sound: None },
+            Note::Tap { beat: Beat::from(2.1), sound: None },
+        ];
+        let expected = [NoteState::Holding(JudgeResult::Hit { tier: 0, delta: Time(0.0) }), NoteState::Pending];
+        for policy in [HitPolicy::CursorLock, HitPolicy::EarliestInWindow, HitPolicy::Nearest] {
+            let (mut nj, mut core, map) = setup_test(notes.clone());
+            core.tail_mode = TailMode::Combined;
+            core.hold_tolerance = Time(0.5);
+            core.hit_policy = policy;
+            nj.on_input(Time(1.0), true, &core, &map);
+            nj.on_input(Time(1.8), false, &core, &map);
+            nj.on_input(Time(2.08), true, &core, &map);
+            assert_eq!(nj.states, expected, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_release_goes_to_held_note() {
+        // Tap 之后 50ms 紧跟 Hold，按 Nearest 按下判给了 Hold，Tap 仍在等待
+        let notes = vec![
+            Note::Tap { beat: Beat::from(1.0), sound: None },
+            Note::Hold { start: Beat::from(1.05), end: Beat::from(2.0), sound: None },
+        ];
+        let (mut nj, mut core, map) = setup_test(notes);
+        core.hit_policy = HitPolicy::Nearest;
+        nj.on_input(Time(1.04), true, &core, &map);
+        assert_eq!(nj.states[0], NoteState::Pending);
+
+        // 松手交给 Hold，容错期内重新按下恢复 Hold，而不是判给 Tap
+        nj.on_input(Time(1.06), false, &core, &map);
+        assert!(matches!(nj.states[1], NoteState::Releasing(..)));
+        nj.on_input(Time(1.062), true, &core, &map);
+        assert_eq!(nj.states[0], NoteState::Pending);
+        assert!(matches!(nj.states[1], NoteState::Holding(JudgeResult::Hit { tier: 0, .. })));
+
+        // 提前松手超过容错，Hold 断掉
+        nj.on_input(Time(1.3), false, &core, &map);
+        nj.update(Time(1.4), &core, &map);
+        assert_eq!(nj.states, vec![NoteState::Missed, NoteState::Missed]);
+    }
+
+    #[test]
+    fn test_ghost_tap_policy() {
+        let notes = vec![
+            Note::Tap { beat: Beat::from(1.0), sound: None },
+            Note::Roll { start: Beat::from(2.0), end: Beat::from(3.0), sound: None },
+        ];
+        let (mut nj, mut core, map) = setup_test(notes.clone());
+        // 默认忽略空按
+        assert_eq!(nj.on_input(Time(0.5), true, &core, &map), None);
+
+        core.ghost_tap = GhostTapPolicy::Count;
+        let mut nj2 = setup_test(notes).0;
+        for judge in [&mut nj, &mut nj2] {
+            // 窗口外按下、松手
+            assert_eq!(judge.on_input(Time(0.5), true, &core, &map), Some(JudgeResult::Empty));
+            assert_eq!(judge.on_input(Time(0.55), false, &core, &map), None);
+        }
+        assert!(matches!(nj.on_input(Time(1.0), true, &core, &map), Some(JudgeResult::Hit { .. })));
+        // Roll 的连打不算空按
+        nj.on_input(Time(2.0), true, &core, &map);
+        assert_eq!(nj.on_input(Time(2.1), true, &core, &map), None);
+        // 音符全部判完之后
+        assert!(nj.update(Time(3.5), &core, &map).iter().all(|(_, r)| matches!(r, JudgeResult::Hit { .. })));
+        assert_eq!(nj.on_input(Time(4.0), true, &core, &map), Some(JudgeResult::Empty));
+    }
+
+    #[test]
+    fn test_ghost_tap_poor_takes_precedence() {
+        let (mut nj, mut core, map) = setup_test(vec![Note::Tap { beat: Beat::from(1.0), sound: None }]);
+        core.window = JudgeWindow::new(core.window.tiers().to_vec(), Some(Time(0.3))).unwrap();
+        core.ghost_tap = GhostTapPolicy::BreakCombo;
+        assert!(matches!(nj.on_input(Time(0.8), true, &core, &map), Some(JudgeResult::Poor { .. })));
+        assert_eq!(nj.on_input(Time(0.5), true, &core, &map), Some(JudgeResult::Empty));
+    }
+
+    #[test]
+    fn test_ghost_tap_policy_from_config() {
+        let policy: GhostTapPolicy = serde_json::from_str(r#"{"DrainGauge":{"amount":0.05}}"#).unwrap();
+        assert_eq!(policy, GhostTapPolicy::DrainGauge { amount: 0.05 });
+        assert_eq!(serde_json::from_str::<GhostTapPolicy>(r#""BreakCombo""#).unwrap(), GhostTapPolicy::BreakCombo);
+
+        let (_, mut core, _) = setup_test(vec![]);
+        for amount in [f64::NAN, f64::INFINITY, -0.05] {
+            core.ghost_tap = GhostTapPolicy::DrainGauge { amount };
+            assert!(core.validate().is_err(), "{amount}");
+        }
+        core.ghost_tap = policy;
+        assert!(core.validate().is_ok());
+        core.hold_tolerance = Time(f64::INFINITY);
+        assert!(core.validate().is_err());
+    }
+
+    #[test]
+    fn test_judge_events_carry_context() {
+        let track = Track { id: 3, notes: vec![
+            Note::Tap { beat: Beat::from(1.0), sound: None },
+            Note::Hold { start: Beat::from(2.0), end: Beat::from(3.0), sound: None },
+            Note::Tap { beat: Beat::from(4.0), sound: None },
+        ] };
+        let (_, mut core, map) = setup_test(vec![]);
+        core.tail_mode = TailMode::Separate;
+        core.hold_tick = Some(Beat::new(0, 1, 2));
+        core.ghost_tap = GhostTapPolicy::Count;
+        let mut manager = JudgeManager::new(vec![track], map, core);
+
+        manager.on_input(3, Time(1.02), true);
+        manager.on_input(3, Time(1.02), false);
+        manager.on_input(3, Time(1.5), true);
+        manager.on_input(3, Time(1.5), false);
+        let events = manager.drain_events();
+        assert_eq!(events.len(), 2);
+        let hit = &events[0];
+        assert_eq!((hit.track, hit.note_idx, hit.kind, hit.phase), (3, Some(0), Some(NoteKind::Tap), JudgePhase::Head));
+        assert_eq!((hit.note_time, hit.input_time), (Some(Time(1.0)), Some(Time(1.02))));
+        assert!((hit.delta.unwrap().0 - 0.02).abs() < 1e-9);
+        assert_eq!(events[1], JudgeEvent {
+            track: 3, note_idx: None, kind: None, note_time: None, input_time: Some(Time(1.5)),
+            delta: None, result: JudgeResult::Empty, phase: JudgePhase::Head,
+        });
+        assert!(manager.drain_events().is_empty());
+
+        // Hold：头判、按住经过的 tick、松手的尾判
+        manager.on_input(3, Time(2.0), true);
+        manager.update(Time(2.6));
+        manager.on_input(3, Time(3.0), false);
+        let events = manager.drain_events();
+        let summary: Vec<_> = events.iter()
+            .map(|e| (e.note_idx, e.phase, e.note_time, e.input_time))
+            .collect();
+        assert_eq!(summary, vec![
+            (Some(1), JudgePhase::Head, Some(Time(2.0)), Some(Time(2.0))),
+            (Some(1), JudgePhase::Tick, Some(Time(2.5)), None),
+            (Some(1), JudgePhase::Tail, Some(Time(3.0)), Some(Time(3.0))),
+        ]);
+
+        // 过期的 Miss 没有输入时间
+        let updates = manager.update(Time(4.5));
+        let events = manager.drain_events();
+        assert_eq!(updates.len(), events.len());
+        assert_eq!((events[0].note_idx, events[0].result, events[0].input_time, events[0].delta), (Some(2), JudgeResult::Miss, None, None));
+    }
 }
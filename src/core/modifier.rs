@@ -0,0 +1,174 @@
+//! Gameplay modifiers: pure `Chart -> Chart` transforms applied before the
+//! judge is built. Lanes are the positions in `Chart::tracks`; track ids stay
+//! where they are so key bindings are unaffected, only the notes move.
+
+use crate::core::chart::{Chart, Note};
+use crate::core::rng::SplitMix64;
+use crate::core::timing::Beat;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Modifier {
+    /// Lane order reversed
+    Mirror,
+    /// Whole lanes permuted, same permutation for the entire chart
+    Random { seed: u64 },
+    /// Every note (chord) placed on a random free lane
+    Shuffle { seed: u64 },
+    /// Holds and rolls become taps on their head
+    NoHolds,
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Mirror => write!(f, "MIRROR"),
+            Modifier::Random { seed } => write!(f, "RANDOM#{seed}"),
+            Modifier::Shuffle { seed } => write!(f, "SHUFFLE#{seed}"),
+            Modifier::NoHolds => write!(f, "NOHOLD"),
+        }
+    }
+}
+
+impl Modifier {
+    pub fn apply(&self, chart: &Chart) -> Chart {
+        match *self {
+            Modifier::Mirror => mirror(chart),
+            Modifier::Random { seed } => random(chart, seed),
+            Modifier::Shuffle { seed } => shuffle(chart, seed),
+            Modifier::NoHolds => no_holds(chart),
+        }
+    }
+}
+
+/// Apply modifiers in order
+pub fn apply_all(chart: &Chart, modifiers: &[Modifier]) -> Chart {
+    modifiers.iter().fold(chart.clone(), |c, m| m.apply(&c))
+}
+
+pub fn mirror(chart: &Chart) -> Chart {
+    let lanes = chart.tracks.len();
+    permute_lanes(chart, &(0..lanes).rev().collect::<Vec<_>>())
+}
+
+pub fn random(chart: &Chart, seed: u64) -> Chart {
+    let mut rng = SplitMix64::new(seed);
+    let mut perm: Vec<usize> = (0..chart.tracks.len()).collect();
+    rng.shuffle(&mut perm);
+    permute_lanes(chart, &perm)
+}
+
+/// Lane `i` receives the notes of lane `perm[i]`
+fn permute_lanes(chart: &Chart, perm: &[usize]) -> Chart {
+    let mut result = chart.clone();
+    for (track, &from) in result.tracks.iter_mut().zip(perm) {
+        track.notes = chart.tracks[from].notes.clone();
+    }
+    result
+}
+
+pub fn shuffle(chart: &Chart, seed: u64) -> Chart {
+    let mut rng = SplitMix64::new(seed);
+    let mut notes: Vec<(usize, Note)> = chart.tracks.iter().enumerate()
+        .flat_map(|(lane, t)| t.notes.iter().map(move |n| (lane, n.clone())))
+        .collect();
+    notes.sort_by_key(|(lane, n)| (n.beat(), *lane));
+
+    let mut result = chart.clone();
+    for track in &mut result.tracks {
+        track.notes.clear();
+    }
+
+    // 每条轨道上最后一个音符占用的 (起始拍, 结束拍)，与 `Chart::validate` 的重叠规则一致
+    let mut occupied: Vec<Option<(Beat, Beat)>> = vec![None; chart.tracks.len()];
+    for (original_lane, note) in notes {
+        let (start, end) = (note.beat(), note.end_beat());
+        let free: Vec<usize> = (0..occupied.len())
+            .filter(|&lane| match occupied[lane] {
+                Some((s, e)) => start != s && start >= e,
+                None => true,
+            })
+            .collect();
+        // 同一时刻占用的轨道数与原谱相同，理论上总有空位
+        let lane = if free.is_empty() { original_lane } else { free[rng.below(free.len())] };
+        occupied[lane] = Some((start, end));
+        result.tracks[lane].notes.push(note);
+    }
+    result
+}
+
+pub fn no_holds(chart: &Chart) -> Chart {
+    let mut result = chart.clone();
+    for note in result.tracks.iter_mut().flat_map(|t| t.notes.iter_mut()) {
+        if let Note::Hold { start, sound, .. } | Note::Roll { start, sound, .. } = note {
+            *note = Note::Tap { beat: *start, sound: sound.take() };
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{ChartMeta, Severity, Track};
+    use crate::core::timing::{BpmChange, Time, TimingMap};
+
+    fn chart() -> Chart {
+        let lanes = vec![
+            vec![Note::Tap { beat: Beat::from_int(0), sound: None }, Note::Hold { start: Beat::from_int(1), end: Beat::from_int(3), sound: None }],
+            vec![Note::Tap { beat: Beat::from_int(1), sound: None }, Note::Roll { start: Beat::from_int(2), end: Beat::from_int(4), sound: None }],
+            vec![Note::Tap { beat: Beat::from_int(1), sound: None }, Note::Tap { beat: Beat::from_int(2), sound: None }],
+            vec![Note::Mine { beat: Beat::from_int(2) }, Note::Tap { beat: Beat::from_int(3), sound: None }],
+        ];
+        Chart {
+            meta: ChartMeta { charter: "t".into(), level: 1, desc: "".into() },
+            timing_map: TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 120.0 }]).unwrap(),
+            tracks: lanes.into_iter().enumerate().map(|(i, notes)| Track { id: i as u8, notes }).collect(),
+            bgm: vec![],
+        }
+    }
+
+    fn note_count(c: &Chart) -> usize {
+        c.tracks.iter().map(|t| t.notes.len()).sum()
+    }
+
+    #[test]
+    fn test_mirror_reverses_lanes() {
+        let original = chart();
+        let mirrored = mirror(&original);
+        assert_eq!(mirrored.tracks[0].id, 0);
+        assert_eq!(mirrored.tracks[0].notes, original.tracks[3].notes);
+        assert_eq!(mirrored.tracks[3].notes, original.tracks[0].notes);
+        assert_eq!(mirror(&mirrored).tracks[1].notes, original.tracks[1].notes);
+    }
+
+    #[test]
+    fn test_random_is_seeded_permutation() {
+        let original = chart();
+        let a = random(&original, 42);
+        assert_eq!(a.tracks.iter().map(|t| t.notes.clone()).collect::<Vec<_>>(),
+                   random(&original, 42).tracks.iter().map(|t| t.notes.clone()).collect::<Vec<_>>());
+        for track in &a.tracks {
+            assert!(original.tracks.iter().any(|t| t.notes == track.notes));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_keeps_notes_without_overlap() {
+        let original = chart();
+        for seed in 0..50 {
+            let shuffled = shuffle(&original, seed);
+            assert_eq!(note_count(&shuffled), note_count(&original));
+            assert!(!shuffled.validate().iter().any(|d| d.severity == Severity::Warning || d.severity == Severity::Error));
+        }
+    }
+
+    #[test]
+    fn test_no_holds_and_apply_all() {
+        let c = apply_all(&chart(), &[Modifier::NoHolds, Modifier::Mirror]);
+        assert!(c.tracks.iter().flat_map(|t| &t.notes).all(|n| matches!(n, Note::Tap { .. } | Note::Mine { .. })));
+        assert_eq!(c.tracks[3].notes[1], Note::Tap { beat: Beat::from_int(1), sound: None });
+        assert_eq!(Modifier::Shuffle { seed: 7 }.to_string(), "SHUFFLE#7");
+    }
+}
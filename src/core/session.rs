@@ -0,0 +1,395 @@
+//! 不依赖终端和音频的完整判定 + 计分流程，`PlayingState` 和测试/bot 共用
+
+use std::collections::HashMap;
+use anyhow::bail;
+use crate::core::chart::Chart;
+use crate::core::judge::{ComboEffect, GhostTapPolicy, JudgeCore, JudgeEvent, JudgeManager, JudgeResult};
+use crate::core::timing::Time;
+
+/// 一个音符满分
+const NOTE_SCORE: u64 = 1000;
+/// 每个 hold tick 的分数
+const TICK_SCORE: u64 = 100;
+
+/// 生命值变化：连 combo 的判定回复，断 combo 的扣除；空按扣多少由 GhostTapPolicy 决定
+const GAUGE_GAIN: f64 = 0.01;
+const GAUGE_TICK_GAIN: f64 = 0.002;
+const GAUGE_BREAK: f64 = 0.02;
+const GAUGE_MISS: f64 = 0.05;
+
+/// A key press or release on a track at chart time `time`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyInput {
+    pub time: Time,
+    pub track: u8,
+    pub is_down: bool,
+}
+
+/// Produces key inputs for a play, e.g. a bot or a recorded replay
+pub trait InputSource {
+    /// Inputs with `time <= now` not returned before, in time order
+    fn poll(&mut self, now: Time) -> Vec<KeyInput>;
+}
+
+/// Plays back a fixed list of inputs
+#[derive(Debug, Clone, Default)]
+pub struct Scripted {
+    inputs: Vec<KeyInput>,
+    next: usize,
+}
+
+impl Scripted {
+    /// `inputs` 按时间稳定排序，同一时刻保持原顺序
+    pub fn new(mut inputs: Vec<KeyInput>) -> Self {
+        inputs.sort_by(|a, b| a.time.0.total_cmp(&b.time.0));
+        Self { inputs, next: 0 }
+    }
+
+    pub fn inputs(&self) -> &[KeyInput] {
+        &self.inputs
+    }
+}
+
+impl InputSource for Scripted {
+    fn poll(&mut self, now: Time) -> Vec<KeyInput> {
+        let due = self.inputs[self.next..].iter().take_while(|i| i.time <= now).count();
+        self.next += due;
+        self.inputs[self.next - due..self.next].to_vec()
+    }
+}
+
+/// Outcome of [`GameSession::simulate`]
+#[derive(Debug, Clone)]
+pub struct SessionResult {
+    pub score: u64,
+    pub max_combo: u32,
+    pub accuracy: f64, // 0.0..=101.0
+    pub events: Vec<JudgeEvent>,
+}
+
+/// Judging and scoring of one play, driven by key inputs and a clock
+pub struct GameSession {
+    pub manager: JudgeManager,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub score: u64,
+    pub max_theoretical_score: u64,
+    pub tier_counts: Vec<u32>, // 与 JudgeWindow::tiers 一一对应
+    pub miss_count: u32,
+    pub poor_count: u32, // 提前空按，不消耗音符
+    pub tick_count: u32, // 按住通过的 hold tick
+    pub tick_miss_count: u32,
+    pub mine_hit_count: u32,
+    pub empty_count: u32, // 空按次数
+    pub gauge: f64, // 生命值 0..=1，只显示，不会失败
+    pub key_pressed: HashMap<u8, bool>,
+    /// 已结算的全部事件
+    pub log: Vec<JudgeEvent>,
+}
+
+impl GameSession {
+    /// `chart` 已应用 modifier，`rate` 倍速下谱面时间按此缩放
+    pub fn new(chart: &Chart, core: JudgeCore, rate: f64) -> anyhow::Result<Self> {
+        let total_notes: usize = chart.tracks.iter()
+            .flat_map(|t| &t.notes)
+            .map(|n| core.judgment_count(n))
+            .sum();
+        let total_ticks: usize = chart.tracks.iter()
+            .flat_map(|t| &t.notes)
+            .map(|n| core.hold_ticks(n).len())
+            .sum();
+        let max_score = total_notes as u64 * NOTE_SCORE + total_ticks as u64 * TICK_SCORE;
+        let tier_count = core.window.tiers().len();
+        let mut manager = JudgeManager::new(
+            chart.tracks.clone(),
+            chart.timing_map.scaled(rate)?,
+            core,
+        );
+        manager.judges.sort_by_key(|j| j.id);
+
+        Ok(Self {
+            manager,
+            combo: 0,
+            max_combo: 0,
+            score: 0,
+            max_theoretical_score: max_score,
+            tier_counts: vec![0; tier_count],
+            miss_count: 0,
+            poor_count: 0,
+            tick_count: 0,
+            tick_miss_count: 0,
+            mine_hit_count: 0,
+            empty_count: 0,
+            gauge: 1.0,
+            key_pressed: HashMap::new(),
+            log: vec![],
+        })
+    }
+
+    /// 按下/松开，忽略按住时的重复按下；返回这次结算的事件
+    pub fn on_key(&mut self, input: KeyInput) -> &[JudgeEvent] {
+        let pressed = self.key_pressed.entry(input.track).or_insert(false);
+        if input.is_down && *pressed {
+            return &[];
+        }
+        *pressed = input.is_down;
+        self.manager.on_input(input.track, input.time, input.is_down);
+        self.process_events()
+    }
+
+    /// 推进时钟到 `now`（自动 Miss 等），返回这次结算的事件
+    pub fn update(&mut self, now: Time) -> &[JudgeEvent] {
+        self.manager.update(now);
+        self.process_events()
+    }
+
+    /// 歌曲结束：剩余 tick 和未判定的音符全部算断
+    pub fn finish(&mut self) {
+        self.manager.finish();
+        self.process_events();
+    }
+
+    /// Last moment any note can still be judged
+    pub fn end_time(&self) -> Time {
+        let core = &self.manager.core;
+        let late = core.window.max_late().0 * core.tail_window_scale.max(1.0);
+        let last = self.manager.judges.iter()
+            .flat_map(|j| &j.notes)
+            .map(|n| self.manager.map.beat_to_time(&n.end_beat()).0)
+            .fold(0.0, f64::max);
+        Time(last + late.max(core.mine_window.0))
+    }
+
+    /// 无终端地跑完整张谱：按 `frame` 步进时钟，每帧先处理到期的输入再 update，
+    /// 与游戏主循环一致
+    pub fn simulate(mut self, source: &mut impl InputSource, frame: Time) -> anyhow::Result<SessionResult> {
+        if !(frame.0.is_finite() && frame.0 > 0.0) {
+            bail!("frame must be a positive finite time, got {}", frame.0);
+        }
+        let end = self.end_time();
+        let mut frame_idx = 0;
+        loop {
+            let now = Time(frame_idx as f64 * frame.0);
+            for input in source.poll(now) {
+                self.on_key(input);
+            }
+            self.update(now);
+            if now > end {
+                break;
+            }
+            frame_idx += 1;
+        }
+        self.finish();
+        Ok(SessionResult {
+            score: self.score,
+            max_combo: self.max_combo,
+            accuracy: self.accuracy_pct(),
+            events: self.log,
+        })
+    }
+
+    /// 结算判定器记录的全部事件，返回新结算的部分
+    fn process_events(&mut self) -> &[JudgeEvent] {
+        let start = self.log.len();
+        for event in self.manager.drain_events() {
+            self.apply(event.result);
+            self.log.push(event);
+        }
+        &self.log[start..]
+    }
+
+    fn apply(&mut self, result: JudgeResult) {
+        match result {
+            JudgeResult::Hit { tier, .. } => {
+                self.tier_counts[tier] += 1;
+                let tier = &self.manager.core.window.tiers()[tier];
+                self.score += (tier.weight * NOTE_SCORE as f64).round() as u64;
+                match tier.combo {
+                    ComboEffect::Continue => {
+                        self.combo += 1;
+                        self.max_combo = self.max_combo.max(self.combo);
+                        self.change_gauge(GAUGE_GAIN);
+                    }
+                    ComboEffect::Break => {
+                        self.combo = 0;
+                        self.change_gauge(-GAUGE_BREAK);
+                    }
+                }
+            }
+            JudgeResult::Poor { .. } => {
+                self.poor_count += 1;
+                self.combo = 0;
+                self.change_gauge(-GAUGE_BREAK);
+            }
+            JudgeResult::Miss => {
+                self.miss_count += 1;
+                self.combo = 0;
+                self.change_gauge(-GAUGE_MISS);
+            }
+            JudgeResult::MineHit => {
+                self.mine_hit_count += 1;
+                self.combo = 0;
+                self.change_gauge(-GAUGE_MISS);
+            }
+            JudgeResult::Tick { held: true } => {
+                self.tick_count += 1;
+                self.score += TICK_SCORE;
+                self.combo += 1;
+                self.max_combo = self.max_combo.max(self.combo);
+                self.change_gauge(GAUGE_TICK_GAIN);
+            }
+            JudgeResult::Tick { held: false } => {
+                self.tick_miss_count += 1;
+                self.combo = 0;
+                self.change_gauge(-GAUGE_BREAK);
+            }
+            JudgeResult::Empty => {
+                self.empty_count += 1;
+                match self.manager.core.ghost_tap {
+                    GhostTapPolicy::BreakCombo => self.combo = 0,
+                    GhostTapPolicy::DrainGauge { amount } => self.change_gauge(-amount),
+                    GhostTapPolicy::Ignore | GhostTapPolicy::Count => {}
+                }
+            }
+        }
+    }
+
+    fn change_gauge(&mut self, amount: f64) {
+        self.gauge = (self.gauge + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn accuracy_pct(&self) -> f64 {
+        if self.max_theoretical_score == 0 {
+            return 0.0;
+        }
+        (self.score as f64 / self.max_theoretical_score as f64) * 101.0
+    }
+
+    /// 计算当前理论最高准度 (Potential Accuracy)
+    /// 逻辑：(当前分数 + 剩余音符全部最高档的分数) / 总分
+    pub fn potential_accuracy_pct(&self) -> f64 {
+        if self.max_theoretical_score == 0 {
+            return 101.0;
+        }
+
+        // 1. 获取所有轨道中还未被判定的音符总数
+        let remaining_notes: usize = self
+            .manager
+            .judges
+            .iter()
+            .map(|j| j.unjudged_count(&self.manager.core))
+            .sum();
+        let remaining_ticks: usize = self.manager.judges.iter()
+            .map(|j| j.unresolved_ticks())
+            .sum();
+
+        // 2. 假设剩下的全是最高档 (每个 1000 分)，tick 全部按住
+        let potential_score = self.score + remaining_notes as u64 * NOTE_SCORE + remaining_ticks as u64 * TICK_SCORE;
+
+        // 3. 映射到 101.0 基准
+        (potential_score as f64 / self.max_theoretical_score as f64) * 101.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{ChartMeta, Note, Track};
+    use crate::core::judge::{JudgePhase, JudgeWindow, TailMode};
+    use crate::core::timing::{Beat, BpmChange, TimingMap};
+
+    /// 60 BPM，一拍一秒：轨道 0 在 1s 一个 Tap，轨道 1 在 2s~3s 一个 Hold
+    fn setup() -> (Chart, JudgeCore) {
+        let chart = Chart {
+            meta: ChartMeta { charter: "t".into(), level: 0, desc: String::new() },
+            timing_map: TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 60.0 }]).unwrap(),
+            tracks: vec![
+                Track { id: 0, notes: vec![Note::Tap { beat: Beat::from(1.0), sound: None }] },
+                Track { id: 1, notes: vec![Note::Hold { start: Beat::from(2.0), end: Beat::from(3.0), sound: None }] },
+            ],
+            bgm: vec![],
+        };
+        let core = JudgeCore::new(JudgeWindow::classic(Time(0.03), Time(0.08)), Time(0.008));
+        (chart, core)
+    }
+
+    fn key(time: f64, track: u8, is_down: bool) -> KeyInput {
+        KeyInput { time: Time(time), track, is_down }
+    }
+
+    const FRAME: Time = Time(1.0 / 240.0);
+
+    #[test]
+    fn test_simulate_full_combo() {
+        let (chart, core) = setup();
+        let inputs = [key(1.0, 0, true), key(1.05, 0, false), key(2.0, 1, true), key(3.0, 1, false)];
+        let result = GameSession::new(&chart, core, 1.0).unwrap().simulate(&mut Scripted::new(inputs.to_vec()), FRAME).unwrap();
+        assert_eq!(result.score, 2000);
+        assert_eq!(result.max_combo, 2);
+        assert!((result.accuracy - 101.0).abs() < 1e-9);
+        let phases: Vec<_> = result.events.iter().map(|e| (e.track, e.phase)).collect();
+        assert_eq!(phases, vec![(0, JudgePhase::Head), (1, JudgePhase::Tail)]);
+    }
+
+    #[test]
+    fn test_simulate_without_input_misses_everything() {
+        let (chart, core) = setup();
+        let result = GameSession::new(&chart, core, 1.0).unwrap().simulate(&mut Scripted::default(), FRAME).unwrap();
+        assert_eq!((result.score, result.max_combo, result.accuracy), (0, 0, 0.0));
+        assert!(result.events.iter().all(|e| e.result == JudgeResult::Miss && e.input_time.is_none()));
+        assert_eq!(result.events.len(), 2);
+    }
+
+    #[test]
+    fn test_simulate_is_deterministic() {
+        let (chart, core) = setup();
+        // 稍晚的 Tap、提前松开的 Hold
+        let inputs = [key(1.05, 0, true), key(1.1, 0, false), key(1.98, 1, true), key(2.5, 1, false)];
+        let first = GameSession::new(&chart, core.clone(), 1.0).unwrap().simulate(&mut Scripted::new(inputs.to_vec()), FRAME).unwrap();
+        let second = GameSession::new(&chart, core, 1.0).unwrap().simulate(&mut Scripted::new(inputs.to_vec()), FRAME).unwrap();
+        assert_eq!(first.events, second.events);
+        assert_eq!(first.score, second.score);
+        assert!(first.score < 2000);
+    }
+
+    #[test]
+    fn test_simulate_rejects_bad_frame() {
+        let (chart, core) = setup();
+        for frame in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let session = GameSession::new(&chart, core.clone(), 1.0).unwrap();
+            assert!(session.simulate(&mut Scripted::default(), Time(frame)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_repeated_press_is_ignored() {
+        let (chart, mut core) = setup();
+        core.ghost_tap = GhostTapPolicy::Count;
+        let mut session = GameSession::new(&chart, core, 1.0).unwrap();
+        assert_eq!(session.on_key(key(0.5, 0, true)).len(), 1);
+        // 按住时终端重复发送的按下不算空按
+        assert!(session.on_key(key(0.55, 0, true)).is_empty());
+        session.on_key(key(0.6, 0, false));
+        assert!(matches!(session.on_key(key(1.0, 0, true))[0].result, JudgeResult::Hit { tier: 0, .. }));
+        assert_eq!((session.empty_count, session.combo), (1, 1));
+        assert_eq!(session.log.len(), 2);
+    }
+
+    #[test]
+    fn test_finish_logs_leftover_judgments() {
+        let (chart, mut core) = setup();
+        core.tail_mode = TailMode::Separate;
+        core.hold_tick = Some(Beat::from(0.5));
+        let mut session = GameSession::new(&chart, core, 1.0).unwrap();
+        session.on_key(key(1.0, 0, true));
+        // Hold 头判后歌曲就结束了：剩下尾判 Miss 和一个 tick
+        session.on_key(key(2.0, 1, true));
+        session.finish();
+        let leftover: Vec<_> = session.log[2..].iter().map(|e| (e.note_idx, e.phase, e.result)).collect();
+        assert_eq!(leftover, vec![
+            (Some(0), JudgePhase::Tail, JudgeResult::Miss),
+            (Some(0), JudgePhase::Tick, JudgeResult::Tick { held: false }),
+        ]);
+        assert_eq!((session.miss_count, session.tick_miss_count), (1, 1));
+    }
+}
@@ -1,8 +1,11 @@
+use std::fmt;
 use std::ops::{Add, Sub};
+use std::str::FromStr;
+use anyhow::{bail, Context};
 use serde::{Deserialize, Serialize};
 
 /// Time in Seconds
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Time(pub f64); // seconds
 
 impl Add for Time {
@@ -26,14 +29,246 @@ impl Time {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
-pub struct Beat(pub f64);
+/// Exact beat position `whole + num / den`, kept reduced with `0 <= num < den`.
+///
+/// Triplets and fine snaps stay exact, and the denominator is the snap divisor
+/// of the position. Serialized as a plain float when that float parses back to
+/// the same fraction, otherwise as `[whole, num, den]` (Malody's beat layout).
+/// Both forms are accepted when reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "BeatRepr", into = "BeatRepr")]
+pub struct Beat {
+    whole: i64,
+    num: u32,
+    den: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BeatRepr {
+    Float(f64),
+    Fraction(i64, u32, u32),
+}
+
+/// Floats closer than this to a fraction are snapped to it
+const BEAT_EPSILON: f64 = 1e-9;
+const MAX_FLOAT_DENOMINATOR: u64 = 1 << 20;
+
+impl Beat {
+    pub const ZERO: Beat = Beat { whole: 0, num: 0, den: 1 };
+
+    /// Panics on a zero denominator or a beat outside the `i64` range,
+    /// parsers use [`Beat::try_new`]
+    pub fn new(whole: i64, num: u32, den: u32) -> Self {
+        Self::try_new(whole, num, den).expect("Invalid beat")
+    }
+
+    pub fn try_new(whole: i64, num: u32, den: u32) -> anyhow::Result<Self> {
+        if den == 0 {
+            bail!("Beat denominator must not be zero");
+        }
+        Self::try_reduce(i128::from(whole) * i128::from(den) + i128::from(num), i128::from(den))
+            .with_context(|| format!("Beat {whole}+{num}/{den} out of range"))
+    }
+
+    pub fn from_int(whole: i64) -> Self {
+        Self { whole, num: 0, den: 1 }
+    }
+
+    /// Closest fraction to `value`, snapping float noise (e.g. `0.333…`) to
+    /// the simplest fraction within [`BEAT_EPSILON`]. Panics where
+    /// [`Beat::try_from_f64`] fails
+    pub fn from_f64(value: f64) -> Self {
+        Self::try_from_f64(value).expect("Invalid beat")
+    }
+
+    /// Fails on NaN, infinity and values outside the `i64` range
+    pub fn try_from_f64(value: f64) -> anyhow::Result<Self> {
+        if !(value.is_finite() && value.abs() < i64::MAX as f64) {
+            bail!("Beat {value} out of range");
+        }
+        let whole = value.floor();
+        let frac = value - whole;
+
+        // 连分数逼近，取第一个足够接近的渐近分数
+        let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+        let mut x = frac;
+        let (mut num, mut den) = (0u64, 1u64);
+        loop {
+            if (frac - num as f64 / den as f64).abs() <= BEAT_EPSILON {
+                break;
+            }
+            let a = x.floor();
+            let (p2, q2) = (a as u64 * p1 + p0, a as u64 * q1 + q0);
+            if q2 > MAX_FLOAT_DENOMINATOR {
+                break;
+            }
+            (p0, q0, p1, q1) = (p1, q1, p2, q2);
+            (num, den) = (p2, q2);
+            let rest = x - a;
+            if rest < f64::EPSILON {
+                break;
+            }
+            x = 1.0 / rest;
+        }
+
+        Self::try_reduce(whole as i128 * i128::from(den) + i128::from(num), i128::from(den))
+            .with_context(|| format!("Beat {value} out of range"))
+    }
+
+    /// 约分；整数部分超出 i64 时返回 None
+    fn try_reduce(mut total: i128, mut den: i128) -> Option<Self> {
+        let g = i128::try_from(gcd(total.unsigned_abs(), den.unsigned_abs()).max(1)).ok()?;
+        total /= g;
+        den /= g;
+        let Ok(den) = u32::try_from(den) else {
+            // 分母溢出时退回浮点逼近
+            return Self::try_from_f64(total as f64 / den as f64).ok();
+        };
+        let den_wide = i128::from(den);
+        Some(Self {
+            whole: i64::try_from(total.div_euclid(den_wide)).ok()?,
+            num: u32::try_from(total.rem_euclid(den_wide)).ok()?,
+            den,
+        })
+    }
+
+    /// Panics where [`Beat::try_reduce`] fails
+    fn reduce(total: i128, den: i128) -> Self {
+        Self::try_reduce(total, den).expect("Beat out of range")
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.whole as f64 + self.num as f64 / self.den as f64
+    }
+
+    pub fn whole(&self) -> i64 {
+        self.whole
+    }
+
+    pub fn numerator(&self) -> u32 {
+        self.num
+    }
+
+    /// Snap divisor of the position: 1 on whole beats, 3 on triplets, ...
+    pub fn denominator(&self) -> u32 {
+        self.den
+    }
+
+    fn total(&self) -> i128 {
+        self.whole as i128 * self.den as i128 + self.num as i128
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl From<f64> for Beat {
+    fn from(value: f64) -> Self {
+        Self::from_f64(value)
+    }
+}
+
+impl TryFrom<BeatRepr> for Beat {
+    type Error = String;
+
+    fn try_from(repr: BeatRepr) -> Result<Self, Self::Error> {
+        match repr {
+            BeatRepr::Float(v) => Beat::try_from_f64(v),
+            BeatRepr::Fraction(whole, num, den) => Beat::try_new(whole, num, den),
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+impl From<Beat> for BeatRepr {
+    fn from(beat: Beat) -> Self {
+        if Beat::from_f64(beat.as_f64()) == beat {
+            BeatRepr::Float(beat.as_f64())
+        } else {
+            BeatRepr::Fraction(beat.whole, beat.num, beat.den)
+        }
+    }
+}
+
+impl Ord for Beat {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.total() * other.den as i128).cmp(&(other.total() * self.den as i128))
+    }
+}
+
+impl PartialOrd for Beat {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Beat {
+    type Output = Beat;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let den = self.den as i128 * rhs.den as i128;
+        Self::reduce(self.total() * rhs.den as i128 + rhs.total() * self.den as i128, den)
+    }
+}
+
+impl Sub for Beat {
+    type Output = Beat;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let den = self.den as i128 * rhs.den as i128;
+        Self::reduce(self.total() * rhs.den as i128 - rhs.total() * self.den as i128, den)
+    }
+}
+
+/// Parses the [`fmt::Display`] form (`3`, `2+1/3`, `-1+3/4`) or a decimal
+impl FromStr for Beat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // 整数部分可能带负号，从第二个字符开始找 '+'
+        if let Some(plus) = s.get(1..).and_then(|rest| rest.find('+')).map(|i| i + 1)
+            && s.contains('/')
+        {
+            let whole: i64 = s[..plus].parse().with_context(|| format!("Invalid beat '{s}'"))?;
+            let (num, den) = s[plus + 1..].split_once('/').with_context(|| format!("Invalid beat '{s}'"))?;
+            let num: u32 = num.parse().with_context(|| format!("Invalid beat '{s}'"))?;
+            let den: u32 = den.parse().with_context(|| format!("Invalid beat '{s}'"))?;
+            return Beat::try_new(whole, num, den).with_context(|| format!("Invalid beat '{s}'"));
+        }
+        let value: f64 = s.parse().with_context(|| format!("Invalid beat '{s}'"))?;
+        Beat::try_from_f64(value).with_context(|| format!("Invalid beat '{s}'"))
+    }
+}
+
+impl fmt::Display for Beat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.num == 0 {
+            write!(f, "{}", self.whole)
+        } else {
+            write!(f, "{}+{}/{}", self.whole, self.num, self.den)
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TimingMap {
     // 如果谱面比音乐快，请往正方向调
-    pub offset: Time,                // Time at Beat(0.0)
-    pub bpm_changes: Vec<BpmChange>, // sorted by beat
+    offset: Time,                // Time at Beat(0.0)
+    bpm_changes: Vec<BpmChange>, // sorted by beat
+    #[serde(default)]
+    stops: Vec<Stop>,            // sorted by beat
+    #[serde(default)]
+    scroll_velocities: Vec<ScrollVelocity>, // sorted by beat
+    #[serde(default)]
+    time_signatures: Vec<TimeSignature>,    // sorted by beat, 4/4 if empty
+    #[serde(skip)]
+    index: TimingIndex,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -42,96 +277,614 @@ pub struct BpmChange {
     pub bpm: f64,
 }
 
+/// Pauses beat progression for `duration` once `beat` is reached
+/// (StepMania `#STOPS`, BMS `#STOP`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stop {
+    pub beat: Beat,
+    pub duration: Time,
+}
+
+/// Scales scroll speed by `multiplier` from `beat` on, independent of BPM.
+/// Before the first change the multiplier is 1.0.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScrollVelocity {
+    pub beat: Beat,
+    pub multiplier: f64,
+}
+
+/// Meter change, a new measure starts at `beat`.
+/// A beat is a quarter note, so 6/8 spans 3 beats per measure.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeSignature {
+    pub beat: Beat,
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl TimeSignature {
+    pub fn beats_per_measure(&self) -> f64 {
+        self.numerator as f64 * 4.0 / self.denominator as f64
+    }
+}
+
+const DEFAULT_BEATS_PER_MEASURE: f64 = 4.0;
+
+/// Precomputed lookup tables, rebuilt by [`TimingMap::build`]
+#[derive(Debug, Clone, Default)]
+struct TimingIndex {
+    /// False until the first successful build and after a failed one
+    built: bool,
+    bpm: Vec<BpmSegment>,
+    stops: Vec<StopSegment>,
+    stopped_before: Vec<f64>, // stopped_before[i] = 前 i 个 stop 的总时长
+    svs: Vec<SvSegment>,
+    measures: Vec<MeasureSegment>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BpmSegment {
+    beat: f64,
+    scroll_time: f64,
+    seconds_per_beat: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StopSegment {
+    beat: Beat,
+    start: f64, // real time the stop begins
+    duration: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SvSegment {
+    beat: Beat,
+    scroll_time: f64,
+    position: f64,
+    multiplier: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MeasureSegment {
+    beat: f64,
+    first_measure: i64,
+    beats_per_measure: f64,
+}
+
 impl TimingMap {
-    /// Convert an absolute beat to absolute time
-    /// bpm_changes must be sorted by beat ascending
-    pub fn beat_to_time(&self, target: &Beat) -> Time {
-        let mut time = self.offset.0;
-
-        for window in self.bpm_changes.windows(2) {
-            let curr = &window[0];
-            let next = &window[1];
-
-            if target < &next.beat {
-                // target is inside this BPM segment
-                let delta_beat = target.0 - curr.beat.0;
-                let seconds_per_beat = 60.0 / curr.bpm;
-                time += delta_beat * seconds_per_beat;
-                return Time(time);
+    /// A validated map with a single timing track, add stops, scroll
+    /// velocities or time signatures with the setters below.
+    pub fn new(offset: Time, bpm_changes: Vec<BpmChange>) -> anyhow::Result<Self> {
+        let mut map = Self {
+            offset,
+            bpm_changes,
+            stops: vec![],
+            scroll_velocities: vec![],
+            time_signatures: vec![],
+            index: TimingIndex::default(),
+        };
+        map.build()?;
+        Ok(map)
+    }
+
+    pub fn offset(&self) -> Time {
+        self.offset
+    }
+
+    pub fn bpm_changes(&self) -> &[BpmChange] {
+        &self.bpm_changes
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops
+    }
+
+    pub fn scroll_velocities(&self) -> &[ScrollVelocity] {
+        &self.scroll_velocities
+    }
+
+    pub fn time_signatures(&self) -> &[TimeSignature] {
+        &self.time_signatures
+    }
+
+    /// Replace the stops and rebuild, see [`Self::build`]
+    pub fn set_stops(&mut self, stops: Vec<Stop>) -> anyhow::Result<()> {
+        self.stops = stops;
+        self.build()
+    }
+
+    /// Replace the scroll velocities and rebuild, see [`Self::build`]
+    pub fn set_scroll_velocities(&mut self, scroll_velocities: Vec<ScrollVelocity>) -> anyhow::Result<()> {
+        self.scroll_velocities = scroll_velocities;
+        self.build()
+    }
+
+    /// Replace the time signatures and rebuild, see [`Self::build`]
+    pub fn set_time_signatures(&mut self, time_signatures: Vec<TimeSignature>) -> anyhow::Result<()> {
+        self.time_signatures = time_signatures;
+        self.build()
+    }
+
+    /// Sort every event list by beat and rebuild, see [`Self::unsorted`]
+    pub fn sort_events(&mut self) -> anyhow::Result<()> {
+        self.bpm_changes.sort_by_key(|c| c.beat);
+        self.stops.sort_by_key(|s| s.beat);
+        self.scroll_velocities.sort_by_key(|sv| sv.beat);
+        self.time_signatures.sort_by_key(|ts| ts.beat);
+        self.build()
+    }
+
+    /// Validate the fields and precompute the segment tables used by every
+    /// conversion. The setters call it; a deserialized map must be built once
+    /// before use. On error the map is left unbuilt.
+    pub fn build(&mut self) -> anyhow::Result<()> {
+        self.index = TimingIndex::default();
+        self.validate()?;
+        for (what, beats) in self.event_beats() {
+            Self::check_sorted(beats.into_iter(), what)?;
+        }
+
+        let mut index = TimingIndex::default();
+
+        let mut scroll_time = self.offset.0;
+        for (i, change) in self.bpm_changes.iter().enumerate() {
+            if let Some(prev) = i.checked_sub(1).map(|p| &self.bpm_changes[p]) {
+                scroll_time += (change.beat - prev.beat).as_f64() * 60.0 / prev.bpm;
+            }
+            index.bpm.push(BpmSegment {
+                beat: change.beat.as_f64(),
+                scroll_time,
+                seconds_per_beat: 60.0 / change.bpm,
+            });
+        }
+        // 后面的表要借 bpm 段换算，之后不会再失败
+        index.built = true;
+        self.index = index;
+
+        let mut stopped = 0.0;
+        let mut stops = Vec::with_capacity(self.stops.len());
+        let mut stopped_before = Vec::with_capacity(self.stops.len() + 1);
+        for stop in &self.stops {
+            stopped_before.push(stopped);
+            stops.push(StopSegment {
+                beat: stop.beat,
+                start: self.beat_to_scroll_time(&stop.beat).0 + stopped,
+                duration: stop.duration.0,
+            });
+            stopped += stop.duration.0;
+        }
+        stopped_before.push(stopped);
+
+        let mut svs: Vec<SvSegment> = Vec::with_capacity(self.scroll_velocities.len());
+        for sv in &self.scroll_velocities {
+            let scroll_time = self.beat_to_scroll_time(&sv.beat).0;
+            let position = match svs.last() {
+                Some(prev) => prev.position + (scroll_time - prev.scroll_time) * prev.multiplier,
+                None => scroll_time,
+            };
+            svs.push(SvSegment { beat: sv.beat, scroll_time, position, multiplier: sv.multiplier });
+        }
+
+        // 不完整的小节也算一个小节
+        let mut measures = vec![MeasureSegment {
+            beat: 0.0,
+            first_measure: 0,
+            beats_per_measure: DEFAULT_BEATS_PER_MEASURE,
+        }];
+        for (i, sig) in self.time_signatures.iter().enumerate() {
+            let last = *measures.last().unwrap();
+            let span = sig.beat.as_f64() - last.beat;
+            if span > 0.0 {
+                measures.push(MeasureSegment {
+                    beat: sig.beat.as_f64(),
+                    first_measure: last.first_measure + (span / last.beats_per_measure - 1e-9).ceil() as i64,
+                    beats_per_measure: sig.beats_per_measure(),
+                });
+            } else {
+                let last = measures.last_mut().unwrap();
+                if i == 0 {
+                    last.beat = sig.beat.as_f64();
+                }
+                last.beats_per_measure = sig.beats_per_measure();
             }
+        }
+
+        self.index.stops = stops;
+        self.index.stopped_before = stopped_before;
+        self.index.svs = svs;
+        self.index.measures = measures;
+        Ok(())
+    }
+
+    /// Value checks on the fields, run by [`Self::build`].
+    /// Ordering is checked separately since charts can repair it by sorting
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.bpm_changes.is_empty() {
+            bail!("The chart does not have a bpm change!");
+        }
+        if let Some(c) = self.bpm_changes.iter().find(|c| !(c.bpm.is_finite() && c.bpm > 0.0)) {
+            bail!("Invalid bpm {} at beat {}", c.bpm, c.beat);
+        }
+        if let Some(s) = self.stops.iter().find(|s| !(s.duration.0.is_finite() && s.duration.0 >= 0.0)) {
+            bail!("Invalid stop duration {} at beat {}", s.duration.0, s.beat);
+        }
+        if let Some(sv) = self.scroll_velocities.iter().find(|sv| !sv.multiplier.is_finite()) {
+            bail!("Invalid scroll velocity {} at beat {}", sv.multiplier, sv.beat);
+        }
+        if let Some(ts) = self.time_signatures.iter().find(|ts| ts.numerator == 0 || ts.denominator == 0) {
+            bail!("Invalid time signature {}/{} at beat {}", ts.numerator, ts.denominator, ts.beat);
+        }
+        Ok(())
+    }
+
+    /// Names of the event lists that are not sorted by beat, which [`Self::build`] rejects
+    pub fn unsorted(&self) -> Vec<&'static str> {
+        self.event_beats().into_iter()
+            .filter(|(_, beats)| !beats.is_sorted())
+            .map(|(what, _)| what)
+            .collect()
+    }
 
-            // consume whole segment
-            let delta_beat = next.beat.0 - curr.beat.0;
-            let seconds_per_beat = 60.0 / curr.bpm;
-            time += delta_beat * seconds_per_beat;
+    fn event_beats(&self) -> [(&'static str, Vec<Beat>); 4] {
+        [
+            ("bpm changes", self.bpm_changes.iter().map(|c| c.beat).collect()),
+            ("stops", self.stops.iter().map(|s| s.beat).collect()),
+            ("scroll velocities", self.scroll_velocities.iter().map(|sv| sv.beat).collect()),
+            ("time signatures", self.time_signatures.iter().map(|ts| ts.beat).collect()),
+        ]
+    }
+
+    fn check_sorted(beats: impl Iterator<Item = Beat>, what: &str) -> anyhow::Result<()> {
+        let mut prev: Option<Beat> = None;
+        for beat in beats {
+            if prev.is_some_and(|p| beat < p) {
+                bail!("The {what} are not sorted by beat (at beat {beat})");
+            }
+            prev = Some(beat);
         }
+        Ok(())
+    }
 
-        // after last bpm change
-        if let Some(last) = self.bpm_changes.last() {
-            let delta_beat = target.0 - last.beat.0;
-            let seconds_per_beat = 60.0 / last.bpm;
-            time += delta_beat * seconds_per_beat;
+    /// The same map played `rate` times as fast: every time is divided by
+    /// `rate`, beats and scroll velocities are unchanged.
+    pub fn scaled(&self, rate: f64) -> anyhow::Result<Self> {
+        if !(rate.is_finite() && rate > 0.0) {
+            bail!("Invalid playback rate {}", rate);
+        }
+        let mut map = self.clone();
+        map.offset = Time(self.offset.0 / rate);
+        for change in &mut map.bpm_changes {
+            change.bpm *= rate;
         }
+        for stop in &mut map.stops {
+            stop.duration = Time(stop.duration.0 / rate);
+        }
+        map.build()?;
+        Ok(map)
+    }
 
-        Time(time)
+    /// Convert an absolute beat to absolute time
+    ///
+    /// A note placed exactly on a stop is timed at the start of the stop.
+    pub fn beat_to_time(&self, target: &Beat) -> Time {
+        let index = self.index();
+        let idx = index.stops.partition_point(|s| s.beat < *target);
+        let stopped = index.stopped_before.get(idx).copied().unwrap_or(0.0);
+        Time(self.beat_to_scroll_time(target).0 + stopped)
     }
 
     /// Convert absolute time to absolute beat
-    /// Assumes bpm_changes is sorted by beat ascending
+    ///
+    /// Every time inside a stop maps back to the stop's beat, so
+    /// `time_to_beat(beat_to_time(b)) == b` holds but the reverse does not
+    /// for times within a frozen span.
     pub fn time_to_beat(&self, target: &Time) -> Beat {
-        let mut current_time = self.offset.0;
+        let index = self.index();
+        let idx = index.stops.partition_point(|s| s.start <= target.0);
+        if let Some(stop) = idx.checked_sub(1).map(|i| &index.stops[i])
+            && target.0 <= stop.start + stop.duration
+        {
+            return stop.beat;
+        }
+        let stopped = index.stopped_before.get(idx).copied().unwrap_or(0.0);
+        self.scroll_time_to_beat(&Time(target.0 - stopped))
+    }
 
-        for window in self.bpm_changes.windows(2) {
-            let curr = &window[0];
-            let next = &window[1];
+    /// Scroll position of a beat, i.e. its time with every stop removed.
+    /// The play field scrolls by this value, so it freezes during stops.
+    pub fn beat_to_scroll_time(&self, target: &Beat) -> Time {
+        let target = target.as_f64();
+        let bpm = &self.index().bpm;
+        let idx = bpm.partition_point(|s| s.beat <= target);
+        // 第一个 bpm change 之前按第一段外推
+        match bpm.get(idx.saturating_sub(1)) {
+            Some(seg) => Time(seg.scroll_time + (target - seg.beat) * seg.seconds_per_beat),
+            None => self.offset,
+        }
+    }
 
-            let seconds_per_beat = 60.0 / curr.bpm;
-            let delta_beats = next.beat.0 - curr.beat.0;
-            let segment_duration = delta_beats * seconds_per_beat;
+    /// Visual position of a beat: scroll time integrated over the scroll
+    /// velocity track. The play field places notes by the difference between
+    /// a note's position and the position of the current time.
+    pub fn beat_to_visual(&self, target: &Beat) -> f64 {
+        let scroll_time = self.beat_to_scroll_time(target).0;
+        let svs = &self.index().svs;
+        let idx = svs.partition_point(|sv| sv.beat < *target);
+        match idx.checked_sub(1).map(|i| &svs[i]) {
+            Some(sv) => sv.position + (scroll_time - sv.scroll_time) * sv.multiplier,
+            None => scroll_time,
+        }
+    }
 
-            let next_time = current_time + segment_duration;
+    /// Visual position of the play field at `time`
+    pub fn time_to_visual(&self, time: &Time) -> f64 {
+        self.beat_to_visual(&self.time_to_beat(time))
+    }
 
-            if target.0 < next_time {
-                // target is inside this BPM segment
-                let delta_time = target.0 - current_time;
-                let beat = curr.beat.0 + delta_time / seconds_per_beat;
-                return Beat(beat);
-            }
+    /// Measure index containing `target` and the beat offset inside it.
+    /// Measure 0 starts at Beat(0.0), or at the first time signature if it
+    /// is placed earlier (pickup measures). A time signature change that is
+    /// not on a bar line cuts the running measure short.
+    pub fn beat_to_measure(&self, target: &Beat) -> (i64, f64) {
+        let target = target.as_f64();
+        let idx = self.index().measures.partition_point(|m| m.beat <= target);
+        let seg = self.measure_segment(idx);
+        let delta = target - seg.beat;
+        let m = (delta / seg.beats_per_measure).floor();
+        (seg.first_measure + m as i64, delta - m * seg.beats_per_measure)
+    }
 
-            // consume whole segment
-            current_time = next_time;
-        }
+    /// Start beat of a measure, inverse of [`TimingMap::beat_to_measure`]
+    pub fn measure_to_beat(&self, measure: i64) -> Beat {
+        let idx = self.index().measures.partition_point(|m| m.first_measure <= measure);
+        let seg = self.measure_segment(idx);
+        Beat::from_f64(seg.beat + (measure - seg.first_measure) as f64 * seg.beats_per_measure)
+    }
 
-        // after last bpm change
-        if let Some(last) = self.bpm_changes.last() {
-            let seconds_per_beat = 60.0 / last.bpm;
-            let delta_time = target.0 - current_time;
-            let beat = last.beat.0 + delta_time / seconds_per_beat;
-            return Beat(beat);
+    fn measure_segment(&self, idx: usize) -> MeasureSegment {
+        self.index().measures.get(idx.saturating_sub(1)).copied().unwrap_or(MeasureSegment {
+            beat: 0.0,
+            first_measure: 0,
+            beats_per_measure: DEFAULT_BEATS_PER_MEASURE,
+        })
+    }
+
+    /// Inverse of [`TimingMap::beat_to_scroll_time`]
+    fn scroll_time_to_beat(&self, target: &Time) -> Beat {
+        let bpm = &self.index().bpm;
+        let idx = bpm.partition_point(|s| s.scroll_time <= target.0);
+        match bpm.get(idx.saturating_sub(1)) {
+            Some(seg) => Beat::from_f64(seg.beat + (target.0 - seg.scroll_time) / seg.seconds_per_beat),
+            None => Beat::ZERO,
         }
+    }
 
-        // unreachable if bpm_changes is non-empty
-        Beat(0.0)
+    /// 所有换算都经过这里；字段只能经 setter 修改，只剩反序列化后忘了 build 的情况
+    fn index(&self) -> &TimingIndex {
+        debug_assert!(self.index.built, "TimingMap used before build()");
+        &self.index
     }
 }
 
-
 #[test]
 fn test_inverse_mapping() {
-    let map = TimingMap {
-        offset: Time(-0.5),
-        bpm_changes: vec![
-            BpmChange { beat: Beat(0.0), bpm: 120.0 },
-            BpmChange { beat: Beat(4.0), bpm: 240.0 },
-        ],
-    };
+    let map = TimingMap::new(Time(-0.5), vec![
+        BpmChange { beat: Beat::from(0.0), bpm: 120.0 },
+        BpmChange { beat: Beat::from(4.0), bpm: 240.0 },
+    ]).unwrap();
 
     let beats = [ -1.0, 0.0, 2.0, 4.0, 6.0 ];
 
     for &b in &beats {
-        let beat = Beat(b);
+        let beat = Beat::from(b);
         let time = map.beat_to_time(&beat);
         let back = map.time_to_beat(&time);
-        assert!((back.0 - b).abs() < 1e-6);
+        assert!((back.as_f64() - b).abs() < 1e-6);
     }
 }
+
+
+#[test]
+fn test_inverse_mapping_with_stops() {
+    let mut map = TimingMap::new(Time(-0.5), vec![
+        BpmChange { beat: Beat::from(0.0), bpm: 120.0 },
+        BpmChange { beat: Beat::from(4.0), bpm: 240.0 },
+    ]).unwrap();
+    map.set_stops(vec![
+        Stop { beat: Beat::from(2.0), duration: Time(1.0) },
+        Stop { beat: Beat::from(6.0), duration: Time(0.25) },
+    ]).unwrap();
+
+    let beats = [ -1.0, 0.0, 2.0, 3.0, 4.0, 6.0, 7.5 ];
+
+    for &b in &beats {
+        let beat = Beat::from(b);
+        let time = map.beat_to_time(&beat);
+        let back = map.time_to_beat(&time);
+        assert!((back.as_f64() - b).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_stop_freezes_beat_and_scroll() {
+    let mut map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::from(0.0), bpm: 60.0 }]).unwrap();
+    map.set_stops(vec![Stop { beat: Beat::from(2.0), duration: Time(1.5) }]).unwrap();
+
+    // 停顿开始于 2.0s，持续到 3.5s
+    assert!((map.beat_to_time(&Beat::from(2.0)).0 - 2.0).abs() < 1e-6);
+    assert!((map.beat_to_time(&Beat::from(3.0)).0 - 4.5).abs() < 1e-6);
+
+    for t in [2.0, 2.7, 3.5] {
+        assert!((map.time_to_beat(&Time(t)).as_f64() - 2.0).abs() < 1e-6);
+        let scroll = map.beat_to_scroll_time(&map.time_to_beat(&Time(t)));
+        assert!((scroll.0 - 2.0).abs() < 1e-6);
+    }
+    assert!((map.time_to_beat(&Time(4.0)).as_f64() - 2.5).abs() < 1e-6);
+
+    for duration in [f64::INFINITY, f64::NAN, -1.0] {
+        assert!(map.set_stops(vec![Stop { beat: Beat::from(2.0), duration: Time(duration) }]).is_err(), "{duration}");
+    }
+}
+
+#[test]
+fn test_visual_position_integrates_sv() {
+    let mut map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::from(0.0), bpm: 60.0 }]).unwrap();
+    map.set_stops(vec![Stop { beat: Beat::from(5.0), duration: Time(1.0) }]).unwrap();
+    map.set_scroll_velocities(vec![
+        ScrollVelocity { beat: Beat::from(2.0), multiplier: 2.0 },
+        ScrollVelocity { beat: Beat::from(4.0), multiplier: 0.5 },
+    ]).unwrap();
+
+    // 无 SV 影响的区间与 scroll time 一致
+    assert!((map.beat_to_visual(&Beat::from(1.0)) - 1.0).abs() < 1e-6);
+    // 2..4 拍倍速
+    assert!((map.beat_to_visual(&Beat::from(3.0)) - 4.0).abs() < 1e-6);
+    assert!((map.beat_to_visual(&Beat::from(4.0)) - 6.0).abs() < 1e-6);
+    // 4 拍之后半速
+    assert!((map.beat_to_visual(&Beat::from(5.0)) - 6.5).abs() < 1e-6);
+    // 停顿期间画面不动
+    assert!((map.time_to_visual(&Time(5.5)) - 6.5).abs() < 1e-6);
+    assert!((map.time_to_visual(&Time(7.0)) - 7.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_beat_to_measure() {
+    let mut map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::from(0.0), bpm: 120.0 }]).unwrap();
+    map.set_time_signatures(vec![
+        TimeSignature { beat: Beat::from(0.0), numerator: 4, denominator: 4 },
+        TimeSignature { beat: Beat::from(8.0), numerator: 6, denominator: 8 },
+        // 不在小节线上的变化：第 4 小节被截断
+        TimeSignature { beat: Beat::from(15.0), numerator: 2, denominator: 4 },
+    ]).unwrap();
+
+    assert_eq!(map.beat_to_measure(&Beat::from(0.0)), (0, 0.0));
+    assert_eq!(map.beat_to_measure(&Beat::from(5.0)), (1, 1.0));
+    assert_eq!(map.beat_to_measure(&Beat::from(8.0)), (2, 0.0));
+    assert_eq!(map.beat_to_measure(&Beat::from(12.5)), (3, 1.5));
+    assert_eq!(map.beat_to_measure(&Beat::from(14.5)), (4, 0.5));
+    assert_eq!(map.beat_to_measure(&Beat::from(15.0)), (5, 0.0));
+    assert_eq!(map.beat_to_measure(&Beat::from(18.0)), (6, 1.0));
+    assert_eq!(map.beat_to_measure(&Beat::from(-1.0)), (-1, 3.0));
+
+    for m in -2..8 {
+        let beat = map.measure_to_beat(m);
+        assert_eq!(map.beat_to_measure(&beat), (m, 0.0));
+    }
+}
+
+#[test]
+fn test_build_rejects_invalid_map() {
+    assert!(TimingMap::new(Time(0.0), vec![]).is_err());
+    assert!(TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::from(0.0), bpm: 0.0 }]).is_err());
+    assert!(TimingMap::new(Time(0.0), vec![
+        BpmChange { beat: Beat::from(4.0), bpm: 120.0 },
+        BpmChange { beat: Beat::from(0.0), bpm: 120.0 },
+    ]).is_err());
+
+    let mut map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::from(0.0), bpm: 120.0 }]).unwrap();
+    assert!(map.set_stops(vec![
+        Stop { beat: Beat::from(2.0), duration: Time(1.0) },
+        Stop { beat: Beat::from(1.0), duration: Time(1.0) },
+    ]).is_err());
+    // 排好序后可用
+    map.sort_events().unwrap();
+    assert_eq!(map.beat_to_time(&Beat::from(3.0)), Time(3.5));
+}
+
+#[test]
+fn test_many_bpm_changes() {
+    // 每拍一次变速，与逐段累加的结果对照
+    let changes: Vec<BpmChange> = (0..500)
+        .map(|i| BpmChange { beat: Beat::from(i as f64), bpm: 100.0 + (i % 7) as f64 * 10.0 })
+        .collect();
+    let expected: Vec<f64> = changes
+        .iter()
+        .scan(0.25, |t, c| {
+            let start = *t;
+            *t += 60.0 / c.bpm;
+            Some(start)
+        })
+        .collect();
+    let map = TimingMap::new(Time(0.25), changes).unwrap();
+
+    for (i, &t) in expected.iter().enumerate() {
+        let beat = Beat::from(i as f64 + 0.5);
+        let time = map.beat_to_time(&Beat::from(i as f64));
+        assert!((time.0 - t).abs() < 1e-9);
+        let back = map.time_to_beat(&map.beat_to_time(&beat));
+        assert!((back.as_f64() - beat.as_f64()).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_beat_fraction_and_serde() {
+    let third = Beat::new(0, 1, 3);
+    assert_eq!(Beat::from(1.0 / 3.0), third);
+    assert_eq!(Beat::new(1, 6, 4), Beat::new(2, 1, 2));
+    assert_eq!(Beat::new(2, 1, 2).denominator(), 2);
+    assert_eq!(Beat::from(-0.25), Beat::new(-1, 3, 4));
+    assert!(Beat::new(0, 1, 3) < Beat::new(0, 1, 2));
+    assert_eq!(third + third + third, Beat::from_int(1));
+    assert_eq!(Beat::new(3, 47, 48) - Beat::new(0, 95, 48), Beat::new(2, 0, 1));
+
+    // 浮点与分数两种写法都能读
+    let from_float: Beat = serde_json::from_str("2.5").unwrap();
+    let from_frac: Beat = serde_json::from_str("[2, 1, 2]").unwrap();
+    assert_eq!(from_float, from_frac);
+    assert!(serde_json::from_str::<Beat>("[2, 1, 0]").is_err());
+    // 超出 i64 的拍不能悄悄回绕
+    let max = i64::MAX;
+    assert!(serde_json::from_str::<Beat>(&format!("[{max}, 5, 1]")).is_err());
+    assert!(serde_json::from_str::<Beat>("1e300").is_err());
+    assert!(Beat::try_new(max, 1, 2).is_ok());
+    assert!(format!("{max}+3/1").parse::<Beat>().is_err());
+
+    // 能被浮点精确还原的写成浮点，否则写成分数
+    for beat in [Beat::new(7, 1, 48), Beat::new(-3, 2, 3), Beat::new(1, 1, 999_983), Beat::from_int(4)] {
+        let json = serde_json::to_string(&beat).unwrap();
+        assert_eq!(serde_json::from_str::<Beat>(&json).unwrap(), beat);
+    }
+    assert_eq!(serde_json::to_string(&Beat::new(1, 1, 4)).unwrap(), "1.25");
+    assert_eq!(serde_json::to_string(&Beat::new(1, 1, 999_983)).unwrap(), "[1,1,999983]");
+
+    for beat in [Beat::new(2, 1, 3), Beat::new(-1, 3, 4), Beat::from_int(-7), Beat::ZERO] {
+        assert_eq!(beat.to_string().parse::<Beat>().unwrap(), beat);
+    }
+    assert_eq!("1.5".parse::<Beat>().unwrap(), Beat::new(1, 1, 2));
+    assert!("1+1/0".parse::<Beat>().is_err());
+    assert!("abc".parse::<Beat>().is_err());
+}
+
+#[test]
+fn test_scaled_map() {
+    let mut map = TimingMap::new(Time(0.4), vec![
+        BpmChange { beat: Beat::ZERO, bpm: 120.0 },
+        BpmChange { beat: Beat::from_int(8), bpm: 180.0 },
+    ]).unwrap();
+    map.set_stops(vec![Stop { beat: Beat::from_int(4), duration: Time(1.0) }]).unwrap();
+
+    let fast = map.scaled(2.0).unwrap();
+    for beat in [Beat::ZERO, Beat::from_int(4), Beat::from_int(5), Beat::new(9, 1, 3)] {
+        assert!((fast.beat_to_time(&beat).0 - map.beat_to_time(&beat).0 / 2.0).abs() < 1e-9);
+    }
+    assert_eq!(fast.time_to_beat(&Time(2.0)), map.time_to_beat(&Time(4.0)));
+    assert!(map.scaled(0.0).is_err());
+}
+
+#[test]
+fn test_setters_rebuild_index() {
+    let mut map = TimingMap::new(Time(0.0), vec![BpmChange { beat: Beat::ZERO, bpm: 60.0 }]).unwrap();
+    map.set_stops(vec![Stop { beat: Beat::from_int(4), duration: Time(1.0) }]).unwrap();
+    assert_eq!(map.beat_to_time(&Beat::from_int(8)), Time(9.0));
+    map.set_stops(vec![Stop { beat: Beat::from_int(4), duration: Time(2.0) }]).unwrap();
+    assert_eq!(map.beat_to_time(&Beat::from_int(8)), Time(10.0));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "before build")]
+fn test_conversion_without_build_panics() {
+    // 反序列化得到的 map 还没有 build
+    let map: TimingMap = serde_json::from_str(r#"{"offset": 0.0, "bpm_changes": [{"beat": 0.0, "bpm": 120.0}]}"#).unwrap();
+    map.beat_to_time(&Beat::from_int(8));
+}
@@ -0,0 +1,81 @@
+use log::error;
+use ratatui::crossterm::event::{self, Event};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A terminal event stamped with the moment the input thread read it
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    pub event: Event,
+    pub at: Instant,
+}
+
+/// Reads terminal events on its own thread so input timing does not depend
+/// on how long a frame takes to render
+pub struct InputThread {
+    rx: Receiver<TimedEvent>,
+}
+
+impl InputThread {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("input".into())
+            .spawn(move || read_loop(tx))
+            .expect("failed to spawn input thread");
+        Self { rx }
+    }
+
+    /// 等待至多 `timeout` 直到有事件，然后取出队列里的全部事件（按读取顺序）
+    pub fn drain(&self, timeout: Duration) -> Vec<TimedEvent> {
+        let first = match self.rx.recv_timeout(timeout) {
+            Ok(ev) => ev,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return vec![],
+        };
+        let mut events = vec![first];
+        events.extend(self.rx.try_iter());
+        events
+    }
+}
+
+fn read_loop(tx: Sender<TimedEvent>) {
+    loop {
+        // read 阻塞到有事件，返回后立刻打时间戳
+        let event = match event::read() {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Failed to read terminal event: {e}");
+                return;
+            }
+        };
+        let at = Instant::now();
+        // 主线程退出后接收端被丢弃
+        if tx.send(TimedEvent { event, at }).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+    #[test]
+    fn test_drain_returns_all_queued_in_order() {
+        let (tx, rx) = mpsc::channel();
+        let input = InputThread { rx };
+        assert!(input.drain(Duration::from_millis(1)).is_empty());
+
+        let start = Instant::now();
+        for (i, c) in "abc".chars().enumerate() {
+            let event = Event::Key(KeyEvent::from(KeyCode::Char(c)));
+            tx.send(TimedEvent { event, at: start + Duration::from_millis(i as u64) }).unwrap();
+        }
+        let events = input.drain(Duration::from_millis(1));
+        assert_eq!(events.len(), 3);
+        assert!(events.windows(2).all(|w| w[0].at < w[1].at));
+        assert!(input.drain(Duration::from_millis(1)).is_empty());
+    }
+}
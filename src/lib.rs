@@ -6,6 +6,8 @@ pub mod ui;
 pub mod load;
 pub mod convert;
 mod audio;
+mod input;
 pub mod config;
+pub mod schema;
 mod asset;
 mod rank;
\ No newline at end of file
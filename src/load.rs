@@ -1,4 +1,6 @@
-use crate::core::chart::{json_to_chart, Chart};
+pub mod migrate;
+
+use crate::core::chart::{text_to_chart, value_to_chart, Chart, TEXT_CHART_EXTENSION};
 use crate::models::{IlluAsset, Song, SongAsset, SongMeta};
 use crate::asset::AssetLocation;
 use anyhow::bail;
@@ -8,8 +10,12 @@ use std::fs;
 use std::path::Path;
 use crate::config::{json_to_config, GlobalConfig};
 
+/// Written as the `version` field of song.json, see [`migrate`]
+pub const SONG_FORMAT_VERSION: u32 = 1;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SongConfig {
+    pub version: u32, // 由 migrate 补全，读到的总是当前版本
     pub meta: SongMeta,
     pub audio_file: String,
     pub chart_files: Vec<String>,
@@ -35,9 +41,19 @@ where
     T: AsRef<Path>,
 {
     info!("Reading chart: {:?}", path.as_ref());
-    let chart_json = fs::read_to_string(&path)
+    let chart_str = fs::read_to_string(&path)
         .inspect_err(|e| error!("Error reading chart: {e}"))?;
-    Ok(json_to_chart(&chart_json)
+
+    // 按扩展名选择格式，其余一律当作 json
+    if path.as_ref().extension().is_some_and(|ext| ext == TEXT_CHART_EXTENSION) {
+        return text_to_chart(&chart_str)
+            .inspect_err(|e| error!("Error converting the text to chart: {e}"));
+    }
+    let doc = serde_json::from_str(&chart_str)
+        .inspect_err(|e| error!("Error parsing chart: {e}"))?;
+    let doc = migrate::migrate_chart(doc)
+        .inspect_err(|e| error!("Error migrating chart: {e}"))?;
+    Ok(value_to_chart(doc)
         .inspect_err(|e| error!("Error converting the json to chart: {e}"))?)
 }
 
@@ -77,7 +93,11 @@ fn load_single_song(dir: &Path) -> anyhow::Result<Song> {
         .inspect_err(|e| error!("Error reading file: {e}"))?;
 
     info!("Parsing config: {config_path:?}");
-    let config: SongConfig = serde_json::from_str(&config_str)
+    let doc = serde_json::from_str(&config_str)
+        .inspect_err(|e|error!("Error parsing config: {e}"))?;
+    let doc = migrate::migrate_song(doc)
+        .inspect_err(|e|error!("Error migrating config: {e}"))?;
+    let config: SongConfig = serde_json::from_value(doc)
         .inspect_err(|e|error!("Error parsing config: {e}"))?;
 
     // 2. 构建 Asset 路径 (Local 模式)
@@ -100,10 +120,16 @@ fn load_single_song(dir: &Path) -> anyhow::Result<Song> {
     }
     let mut charts = Vec::new();
     for c_cfg in config.chart_files {
-        let chart = load_chart(dir.join(c_cfg)).inspect_err(
-            |e| error!("Error parsing chart: {e}")
-        )?;
-        charts.push(chart);
+        // 有 Error 级诊断的谱面被拒绝加载，Warning 在解析时已自动修复
+        match load_chart(dir.join(&c_cfg)) {
+            Ok(chart) => charts.push(chart),
+            Err(e) => warn!("Skipping refused chart({c_cfg}): {e}"),
+        }
+    }
+    if charts.is_empty() {
+        let err_msg = "None of the charts could be loaded!";
+        error!("{}", err_msg);
+        bail!("{}", err_msg);
     }
 
     Ok(Song {
@@ -122,6 +148,7 @@ mod tests {
     #[test]
     fn test_gen_song_config() {
         let cfg = SongConfig {
+            version: SONG_FORMAT_VERSION,
             meta: SongMeta {
                 title: "Wow".into(),
                 length: Duration::from_secs(200),
@@ -135,4 +162,23 @@ mod tests {
 
         println!("{}", serde_json::to_string(&cfg).unwrap())
     }
+
+    #[test]
+    fn test_load_chart_by_extension() {
+        let dir = std::env::temp_dir().join(format!("mug-tui-load-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json = r#"{
+            "meta": {"charter": "t", "level": 1, "desc": ""},
+            "timing_map": {"offset": 0.0, "bpm_changes": [{"beat": 0.0, "bpm": 120.0}]},
+            "tracks": [{"id": 0, "notes": [{"Tap": {"beat": 1.0}}]}, {"id": 1, "notes": [{"Hold": {"start": 2.0, "end": 6.0}}]}]
+        }"#;
+        fs::write(dir.join("c.json"), json).unwrap();
+        let from_json = load_chart(dir.join("c.json")).unwrap();
+        let text = crate::core::chart::chart_to_text(&from_json).unwrap();
+        fs::write(dir.join("c.mtc"), text).unwrap();
+        let from_text = load_chart(dir.join("c.mtc")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(from_text.to_value().unwrap(), from_json.to_value().unwrap());
+    }
 }
\ No newline at end of file
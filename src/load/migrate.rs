@@ -0,0 +1,146 @@
+//! Upgrades chart and song.json documents written by older versions to the
+//! current format before they are deserialized.
+//! A document without a `version` field is version 0, the format from before
+//! versioning: a change that old documents cannot be read as bumps the
+//! version and appends a migration here.
+
+use crate::core::chart::CHART_FORMAT_VERSION;
+use crate::load::SONG_FORMAT_VERSION;
+use anyhow::{bail, Context};
+use log::info;
+use serde_json::{Map, Value};
+
+type Migration = fn(&mut Map<String, Value>) -> anyhow::Result<()>;
+
+/// `CHART_MIGRATIONS[v]` upgrades a version `v` chart to `v + 1`
+const CHART_MIGRATIONS: &[Migration] = &[chart_v0_to_v1];
+/// `SONG_MIGRATIONS[v]` upgrades a version `v` song.json to `v + 1`
+const SONG_MIGRATIONS: &[Migration] = &[song_v0_to_v1];
+
+/// Drop the `"type"` tag the first charts were written with and fill in the
+/// fields added since: bgm lane, keysounds, stops, SV and time signatures
+fn chart_v0_to_v1(chart: &mut Map<String, Value>) -> anyhow::Result<()> {
+    chart.remove("type");
+    chart.entry("bgm").or_insert_with(|| Value::Array(vec![]));
+
+    let timing_map = chart
+        .get_mut("timing_map")
+        .and_then(Value::as_object_mut)
+        .context("Missing timing_map")?;
+    for key in ["stops", "scroll_velocities", "time_signatures"] {
+        timing_map.entry(key).or_insert_with(|| Value::Array(vec![]));
+    }
+
+    let tracks = chart.get_mut("tracks").and_then(Value::as_array_mut).context("Missing tracks")?;
+    let notes = tracks
+        .iter_mut()
+        .filter_map(|t| t.get_mut("notes").and_then(Value::as_array_mut))
+        .flatten();
+    // 外部标签的枚举：{"Tap": {...}}，地雷没有 keysound
+    for note in notes.filter_map(Value::as_object_mut) {
+        for (kind, body) in note.iter_mut().filter(|(kind, _)| *kind != "Mine") {
+            let body = body.as_object_mut().with_context(|| format!("Invalid {kind} note"))?;
+            body.entry("sound").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+/// The first song.json could leave out `illu_file`
+fn song_v0_to_v1(song: &mut Map<String, Value>) -> anyhow::Result<()> {
+    song.entry("illu_file").or_insert(Value::Null);
+    Ok(())
+}
+
+pub fn migrate_chart(doc: Value) -> anyhow::Result<Value> {
+    migrate(doc, CHART_MIGRATIONS, CHART_FORMAT_VERSION, "chart")
+}
+
+pub fn migrate_song(doc: Value) -> anyhow::Result<Value> {
+    migrate(doc, SONG_MIGRATIONS, SONG_FORMAT_VERSION, "song config")
+}
+
+fn migrate(mut doc: Value, migrations: &[Migration], current: u32, what: &str) -> anyhow::Result<Value> {
+    let Some(obj) = doc.as_object_mut() else {
+        bail!("The {what} is not a json object");
+    };
+    let mut version = match obj.get("version") {
+        None => 0,
+        Some(v) => v.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .with_context(|| format!("Invalid {what} version {v}"))?,
+    };
+    if version > current {
+        bail!("The {what} has format version {version}, newer than the supported {current}");
+    }
+
+    while version < current {
+        migrations[version as usize](obj)
+            .with_context(|| format!("Failed to migrate the {what} from version {version}"))?;
+        version += 1;
+        info!("Migrated {what} to format version {version}");
+    }
+    obj.insert("version".into(), current.into());
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::json_to_chart;
+    use serde_json::json;
+
+    #[test]
+    fn test_versions_match_migrations() {
+        assert_eq!(CHART_MIGRATIONS.len(), CHART_FORMAT_VERSION as usize);
+        assert_eq!(SONG_MIGRATIONS.len(), SONG_FORMAT_VERSION as usize);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_chart() {
+        // 最早的谱面带有 `#[serde(tag = "type")]` 写出的字段，没有后来加的字段
+        let legacy = json!({
+            "type": "Chart",
+            "meta": {"charter": "t", "level": 1, "desc": ""},
+            "timing_map": {"offset": 0.0, "bpm_changes": [{"beat": 0.0, "bpm": 120.0}]},
+            "tracks": [{"id": 0, "notes": [{"Tap": {"beat": 1.0}}, {"Hold": {"start": 2.0, "end": 3.0}}]}]
+        });
+
+        let migrated = migrate_chart(legacy).unwrap();
+        assert_eq!(migrated["version"], json!(CHART_FORMAT_VERSION));
+        assert!(migrated.get("type").is_none());
+        assert_eq!(migrated["bgm"], json!([]));
+        assert_eq!(migrated["timing_map"]["time_signatures"], json!([]));
+        assert_eq!(migrated["tracks"][0]["notes"][1], json!({"Hold": {"start": 2.0, "end": 3.0, "sound": null}}));
+
+        // 写出的文档已是当前版本，再迁移不变
+        let current = json_to_chart(&migrated.to_string()).unwrap().to_value().unwrap();
+        assert_eq!(migrate_chart(current.clone()).unwrap(), current);
+
+        assert_eq!(migrate_song(json!({"meta": {}})).unwrap(), json!({"meta": {}, "illu_file": null, "version": SONG_FORMAT_VERSION}));
+    }
+
+    #[test]
+    fn test_load_baseline_chart_file() {
+        // 版本化之前的程序写出的谱面文件
+        let path = std::env::temp_dir().join(format!("mug-tui-legacy-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"type":"Chart","meta":{"charter":"SakiMidare","level":10,"desc":"Test chart"},"timing_map":{"offset":0.0,"bpm_changes":[{"beat":0.0,"bpm":180.0}]},"tracks":[{"id":0,"notes":[{"Tap":{"beat":1.0}},{"Hold":{"start":2.0,"end":3.0}}]}]}"#).unwrap();
+        let chart = crate::load::load_chart(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let chart = chart.unwrap();
+        assert_eq!(chart.meta.charter, "SakiMidare");
+        assert_eq!(chart.tracks[0].notes[1].end_beat(), crate::core::timing::Beat::from_int(3));
+        assert!(chart.bgm.is_empty() && chart.timing_map.stops().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_version() {
+        let doc = json!({"version": CHART_FORMAT_VERSION + 1});
+        assert!(migrate_chart(doc.clone()).is_err());
+        // 不经迁移直接解析谱面也拒绝更新的版本
+        assert!(json_to_chart(&doc.to_string()).is_err());
+        assert!(migrate_song(json!({"version": "one"})).is_err());
+        assert!(migrate_song(json!([])).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! JSON Schemas (draft 2020-12) of the chart and song.json formats, so
+//! external editors can validate files. Kept by hand next to the serde
+//! structs, the tests compare them with the serde fields and validate
+//! `example_song/`; `cargo run --bin export_schema` writes them out.
+
+use crate::core::chart::CHART_FORMAT_VERSION;
+use crate::load::SONG_FORMAT_VERSION;
+use serde_json::{json, Value};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+pub fn chart_schema() -> Value {
+    let beat = json!({"$ref": "#/$defs/beat"});
+    let sound = json!({"type": "string", "description": "Sample file relative to the song directory"});
+    let variant = |name: &str, fields: Value, required: Value| json!({
+        "type": "object",
+        "properties": {name: {"type": "object", "properties": fields, "required": required, "additionalProperties": false}},
+        "required": [name],
+        "additionalProperties": false
+    });
+    let at_beat = |extra: Value| {
+        let mut props = json!({"beat": beat});
+        props.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        json!({"type": "object", "properties": props, "required": ["beat"]})
+    };
+
+    json!({
+        "$schema": DRAFT,
+        "title": "mug-tui chart",
+        "type": "object",
+        "properties": {
+            "version": {"const": CHART_FORMAT_VERSION},
+            "meta": {
+                "type": "object",
+                "properties": {
+                    "charter": {"type": "string"},
+                    "level": {"type": "integer", "minimum": 0, "maximum": 255},
+                    "desc": {"type": "string"}
+                },
+                "required": ["charter", "level", "desc"]
+            },
+            "timing_map": {
+                "type": "object",
+                "properties": {
+                    "offset": {"type": "number", "description": "Seconds at beat 0"},
+                    "bpm_changes": {"type": "array", "minItems": 1, "items": at_beat(json!({"bpm": {"type": "number", "exclusiveMinimum": 0}}))},
+                    "stops": {"type": "array", "items": at_beat(json!({"duration": {"type": "number", "minimum": 0}}))},
+                    "scroll_velocities": {"type": "array", "items": at_beat(json!({"multiplier": {"type": "number"}}))},
+                    "time_signatures": {"type": "array", "items": at_beat(json!({
+                        "numerator": {"type": "integer", "minimum": 1},
+                        "denominator": {"type": "integer", "minimum": 1}
+                    }))}
+                },
+                "required": ["offset", "bpm_changes"]
+            },
+            "tracks": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "integer", "minimum": 0, "maximum": 255},
+                        "notes": {"type": "array", "items": {"$ref": "#/$defs/note"}}
+                    },
+                    "required": ["id", "notes"]
+                }
+            },
+            "bgm": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {"beat": beat, "sound": sound},
+                    "required": ["beat", "sound"]
+                }
+            }
+        },
+        "required": ["version", "meta", "timing_map", "tracks"],
+        "$defs": {
+            "beat": {
+                "description": "Quarter notes from beat 0, a number or an exact [whole, numerator, denominator]",
+                "oneOf": [
+                    {"type": "number"},
+                    {
+                        "type": "array",
+                        "prefixItems": [{"type": "integer"}, {"type": "integer", "minimum": 0}, {"type": "integer", "minimum": 1}],
+                        "items": false,
+                        "minItems": 3
+                    }
+                ]
+            },
+            "note": {
+                "oneOf": [
+                    variant("Tap", json!({"beat": beat, "sound": sound}), json!(["beat"])),
+                    variant("Hold", json!({"start": beat, "end": beat, "sound": sound}), json!(["start", "end"])),
+                    variant("Roll", json!({"start": beat, "end": beat, "sound": sound}), json!(["start", "end"])),
+                    variant("Mine", json!({"beat": beat}), json!(["beat"]))
+                ]
+            }
+        }
+    })
+}
+
+pub fn song_schema() -> Value {
+    json!({
+        "$schema": DRAFT,
+        "title": "mug-tui song.json",
+        "type": "object",
+        "properties": {
+            "version": {"const": SONG_FORMAT_VERSION},
+            "meta": {
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "artist": {"type": "string"},
+                    "length": {
+                        "type": "object",
+                        "properties": {
+                            "secs": {"type": "integer", "minimum": 0},
+                            "nanos": {"type": "integer", "minimum": 0}
+                        },
+                        "required": ["secs", "nanos"]
+                    },
+                    "bpm": {"type": "number"}
+                },
+                "required": ["title", "artist", "length", "bpm"]
+            },
+            "audio_file": {"type": "string"},
+            "chart_files": {"type": "array", "items": {"type": "string", "description": "A json chart, or a text chart if it ends in .mtc"}, "minItems": 1},
+            "illu_file": {"type": ["string", "null"]}
+        },
+        "required": ["version", "meta", "audio_file", "chart_files"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chart::{json_to_chart, text_to_chart, BgmEvent, Chart, ChartMeta, Note, Track};
+    use crate::core::timing::{Beat, BpmChange, ScrollVelocity, Stop, TimeSignature, TimingMap};
+    use crate::load::SongConfig;
+    use crate::models::SongMeta;
+    use serde::de::{self, DeserializeOwned, Visitor};
+
+    /// 写出的每个字段都必须出现在 schema 里
+    fn assert_covered(doc: &Value, schema: &Value) {
+        let props = schema["properties"].as_object().unwrap();
+        for key in doc.as_object().unwrap().keys() {
+            assert!(props.contains_key(key), "{key} missing from schema");
+        }
+        for key in schema["required"].as_array().unwrap() {
+            assert!(doc.get(key.as_str().unwrap()).is_some(), "required {key} not written");
+        }
+    }
+
+    #[test]
+    fn test_schemas_cover_written_documents() {
+        let chart = json_to_chart(r#"{
+            "meta": {"charter": "t", "level": 1, "desc": ""},
+            "timing_map": {"offset": 0.0, "bpm_changes": [{"beat": 0.0, "bpm": 120.0}]},
+            "tracks": [{"id": 0, "notes": [{"Tap": {"beat": [0, 1, 3], "sound": "a.wav"}}, {"Mine": {"beat": 2.0}}]}],
+            "bgm": [{"beat": 1.0, "sound": "b.wav"}]
+        }"#).unwrap();
+        let doc = chart.to_value().unwrap();
+        let schema = chart_schema();
+        assert_covered(&doc, &schema);
+        assert_covered(&doc["timing_map"], &schema["properties"]["timing_map"]);
+        assert_eq!(schema["properties"]["version"]["const"], doc["version"]);
+
+        let song = serde_json::to_value(crate::load::SongConfig {
+            version: SONG_FORMAT_VERSION,
+            meta: crate::models::SongMeta {
+                title: "T".into(),
+                artist: "A".into(),
+                length: std::time::Duration::from_secs(1),
+                bpm: 120.0,
+            },
+            audio_file: "a.ogg".into(),
+            chart_files: vec!["c.json".into()],
+            illu_file: None,
+        }).unwrap();
+        let schema = song_schema();
+        assert_covered(&song, &schema);
+        assert_covered(&song["meta"], &schema["properties"]["meta"]);
+    }
+
+    /// 只实现 schema 里用到的关键字，够验证示例文件
+    fn check(doc: &Value, schema: &Value, root: &Value, path: &str) -> Result<(), String> {
+        if let Some(r) = schema["$ref"].as_str() {
+            let name = r.strip_prefix("#/$defs/").ok_or(format!("{path}: unsupported $ref {r}"))?;
+            return check(doc, &root["$defs"][name], root, path);
+        }
+        if let Some(branches) = schema["oneOf"].as_array() {
+            let matched = branches.iter().filter(|b| check(doc, b, root, path).is_ok()).count();
+            if matched != 1 {
+                return Err(format!("{path}: {doc} matches {matched} branches of oneOf"));
+            }
+        }
+        if let Some(c) = schema.get("const") && c != doc {
+            return Err(format!("{path}: expected {c}, got {doc}"));
+        }
+        if let Some(ty) = schema.get("type") {
+            let types: Vec<&str> = match ty {
+                Value::Array(tys) => tys.iter().filter_map(Value::as_str).collect(),
+                ty => vec![ty.as_str().unwrap()],
+            };
+            let is = |t: &str| match t {
+                "object" => doc.is_object(),
+                "array" => doc.is_array(),
+                "string" => doc.is_string(),
+                "number" => doc.is_number(),
+                "integer" => doc.is_i64() || doc.is_u64(),
+                "null" => doc.is_null(),
+                t => panic!("unsupported type {t}"),
+            };
+            if !types.into_iter().any(is) {
+                return Err(format!("{path}: {doc} is not {ty}"));
+            }
+        }
+        if let Some(n) = doc.as_f64() {
+            let bound = |key: &str| schema[key].as_f64();
+            if bound("minimum").is_some_and(|m| n < m)
+                || bound("maximum").is_some_and(|m| n > m)
+                || bound("exclusiveMinimum").is_some_and(|m| n <= m)
+            {
+                return Err(format!("{path}: {n} out of range"));
+            }
+        }
+        if let Some(obj) = doc.as_object() {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                if !obj.contains_key(key.as_str().unwrap()) {
+                    return Err(format!("{path}: missing {key}"));
+                }
+            }
+            for (key, value) in obj {
+                match schema["properties"].get(key) {
+                    Some(prop) => check(value, prop, root, &format!("{path}.{key}"))?,
+                    None if schema["additionalProperties"] == false => return Err(format!("{path}: unexpected {key}")),
+                    None => {}
+                }
+            }
+        }
+        if let Some(items) = doc.as_array() {
+            if schema["minItems"].as_u64().is_some_and(|n| (items.len() as u64) < n) {
+                return Err(format!("{path}: too few items"));
+            }
+            let prefix = schema["prefixItems"].as_array().map_or(&[][..], Vec::as_slice);
+            for (i, item) in items.iter().enumerate() {
+                let item_schema = prefix.get(i).unwrap_or(&schema["items"]);
+                match item_schema {
+                    Value::Bool(false) => return Err(format!("{path}: too many items")),
+                    Value::Null => {}
+                    item_schema => check(item, item_schema, root, &format!("{path}[{i}]"))?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(doc: &Value, schema: &Value) {
+        if let Err(e) = check(doc, schema, schema, "$") {
+            panic!("{e}");
+        }
+    }
+
+    #[test]
+    fn test_example_song_validates() {
+        let song: Value = serde_json::from_str(include_str!("../example_song/song.json")).unwrap();
+        validate(&song, &song_schema());
+
+        let json = include_str!("../example_song/charts/normal.json");
+        validate(&serde_json::from_str(json).unwrap(), &chart_schema());
+        validate(&json_to_chart(json).unwrap().to_value().unwrap(), &chart_schema());
+        let text = text_to_chart(include_str!("../example_song/charts/hard.mtc")).unwrap();
+        validate(&text.to_value().unwrap(), &chart_schema());
+
+        // 校验器本身要能发现错误
+        let mut broken: Value = serde_json::from_str(json).unwrap();
+        broken["tracks"][0]["notes"][0] = serde_json::json!({"Tap": {"beat": 1.0, "sond": "a.wav"}});
+        assert!(check(&broken, &chart_schema(), &chart_schema(), "$").is_err());
+    }
+
+    /// 记下 serde 派生代码声明的字段名，然后放弃解析
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    fn serde_fields<T: DeserializeOwned>() -> Vec<&'static str> {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(FieldNames(&mut fields));
+        let mut fields = fields.to_vec();
+        fields.sort();
+        fields
+    }
+
+    fn schema_fields(schema: &Value) -> Vec<&str> {
+        let mut fields: Vec<&str> = schema["properties"].as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_schema_properties_match_serde_fields() {
+        let chart = chart_schema();
+        let props = &chart["properties"];
+        let at_beat = |name: &str| &props["timing_map"]["properties"][name]["items"];
+        // version 由 to_value 写入，不是结构体字段
+        let mut top = serde_fields::<Chart>();
+        top.push("version");
+        top.sort();
+        assert_eq!(schema_fields(&chart), top);
+        assert_eq!(schema_fields(&props["meta"]), serde_fields::<ChartMeta>());
+        assert_eq!(schema_fields(&props["timing_map"]), serde_fields::<TimingMap>());
+        assert_eq!(schema_fields(at_beat("bpm_changes")), serde_fields::<BpmChange>());
+        assert_eq!(schema_fields(at_beat("stops")), serde_fields::<Stop>());
+        assert_eq!(schema_fields(at_beat("scroll_velocities")), serde_fields::<ScrollVelocity>());
+        assert_eq!(schema_fields(at_beat("time_signatures")), serde_fields::<TimeSignature>());
+        assert_eq!(schema_fields(&props["tracks"]["items"]), serde_fields::<Track>());
+        assert_eq!(schema_fields(&props["bgm"]["items"]), serde_fields::<BgmEvent>());
+
+        // 枚举的字段从写出的完整变体里取
+        let sound = Some("a.wav".to_string());
+        let (start, end) = (Beat::ZERO, Beat::from_int(1));
+        let notes = [
+            Note::Tap { beat: start, sound: sound.clone() },
+            Note::Hold { start, end, sound: sound.clone() },
+            Note::Roll { start, end, sound },
+            Note::Mine { beat: start },
+        ];
+        let variants = chart["$defs"]["note"]["oneOf"].as_array().unwrap();
+        assert_eq!(variants.len(), notes.len());
+        for (note, variant) in notes.iter().zip(variants) {
+            let written = serde_json::to_value(note).unwrap();
+            let (name, fields) = written.as_object().unwrap().iter().next().unwrap();
+            let mut fields: Vec<&str> = fields.as_object().unwrap().keys().map(String::as_str).collect();
+            fields.sort();
+            assert_eq!(schema_fields(&variant["properties"][name]), fields, "{name}");
+        }
+
+        let song = song_schema();
+        assert_eq!(schema_fields(&song), serde_fields::<SongConfig>());
+        assert_eq!(schema_fields(&song["properties"]["meta"]), serde_fields::<SongMeta>());
+    }
+}
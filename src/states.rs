@@ -5,10 +5,11 @@ pub mod result;
 
 use crate::app::AppContext;
 use crate::core::chart::Chart;
+use crate::core::modifier::Modifier;
 use crate::models::{Song, SongAsset};
 use ratatui::crossterm::event::KeyEvent;
 use ratatui::Frame;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use crate::rank::Rank;
 
 pub enum StateAction {
@@ -18,20 +19,24 @@ pub enum StateAction {
     GoToCollection,
     GoToPlaying{
         song: Song,
-        chart: Chart
+        chart: Chart,
+        modifiers: Vec<Modifier>,
+        rate: f64,
     },
     StartAudio {
         song_asset: SongAsset,
+        rate: f64,
     },
     TogglePause,
     ShowResult {
-        score: u32,
+        score: u64,
         rank: Rank,
     },
 }
 
 trait Stateful {
-    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent) -> StateAction;
+    /// `at` 是输入线程读到按键的时刻
+    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent, at: Instant) -> StateAction;
 
     fn draw(&self, ctx: &AppContext, f: &mut Frame);
 
@@ -48,12 +53,12 @@ pub enum State {
 }
 
 impl State {
-    pub fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent) -> StateAction {
+    pub fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent, at: Instant) -> StateAction {
         match self {
-            State::Welcome(s) => s.handle_input(ctx, event),
-            State::Collection(s) => s.handle_input(ctx, event),
-            State::Playing(s) => s.handle_input(ctx, event),
-            State::Result(s) => s.handle_input(ctx, event),
+            State::Welcome(s) => s.handle_input(ctx, event, at),
+            State::Collection(s) => s.handle_input(ctx, event, at),
+            State::Playing(s) => s.handle_input(ctx, event, at),
+            State::Result(s) => s.handle_input(ctx, event, at),
         }
     }
 
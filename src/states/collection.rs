@@ -1,7 +1,11 @@
 use ratatui::crossterm::event::KeyCode::{Char, Down, Enter, Esc, Left, Right, Up};
 use ratatui::crossterm::event::{KeyEvent, KeyEventKind};
 use ratatui::Frame;
+use std::time::{Duration, Instant};
 use crate::app::AppContext;
+use crate::core::analysis::{analyze, ChartAnalysis};
+use crate::core::difficulty::estimate_difficulty;
+use crate::core::modifier::Modifier;
 use crate::states::{StateAction, Stateful};
 use crate::ui;
 
@@ -9,15 +13,46 @@ pub struct CollectionState {
     pub song_cursor: Option<usize>, // 歌曲列表光标
     pub chart_cursor: usize,        // 谱面列表光标（仅在选中歌曲后有效）
     pub is_selecting_chart: bool,   // 状态开关：是选歌还是选谱面
+    pub analysis: Option<((usize, usize), ChartAnalysis)>, // (歌曲, 谱面) 的统计缓存
+    pub ratings: Option<(usize, Vec<f64>)>, // 当前歌曲各谱面的估算难度
+    pub modifiers: Vec<Modifier>,   // 开始游戏时按顺序应用到谱面
+    pub rate: f64,                  // 播放倍速
 }
 
+pub const MIN_RATE: f64 = 0.5;
+pub const MAX_RATE: f64 = 2.0;
+const RATE_STEP: f64 = 0.05;
+
 impl CollectionState {
     pub fn new(song_count: usize) -> Self {
         Self {
             song_cursor: if song_count != 0 { Some(0) } else { None },
             chart_cursor: 0,
             is_selecting_chart: false,
+            analysis: None,
+            ratings: None,
+            modifiers: vec![],
+            rate: 1.0,
+        }
+    }
+
+    /// 开关一个 modifier；Random 与 Shuffle 互斥，打开时重新取种子
+    fn toggle_modifier(&mut self, modifier: Modifier) {
+        let same_kind = |m: &Modifier| std::mem::discriminant(m) == std::mem::discriminant(&modifier);
+        if self.modifiers.iter().any(same_kind) {
+            self.modifiers.retain(|m| !same_kind(m));
+            return;
+        }
+        if matches!(modifier, Modifier::Random { .. } | Modifier::Shuffle { .. }) {
+            self.modifiers.retain(|m| !matches!(m, Modifier::Random { .. } | Modifier::Shuffle { .. }));
         }
+        self.modifiers.push(modifier);
+    }
+
+    /// 详情面板展示的谱面：选谱时为光标所指，否则为第一张
+    pub fn focused_chart(&self) -> Option<(usize, usize)> {
+        let song = self.song_cursor?;
+        Some((song, if self.is_selecting_chart { self.chart_cursor } else { 0 }))
     }
 
     fn move_up(&mut self, song_count: usize, chart_count: usize) {
@@ -41,8 +76,15 @@ impl CollectionState {
     }
 }
 
+fn new_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
 impl Stateful for CollectionState {
-    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent) -> StateAction {
+    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent, _at: Instant) -> StateAction {
         if event.kind != KeyEventKind::Press {
             return StateAction::None;
         }
@@ -81,6 +123,8 @@ impl Stateful for CollectionState {
                         return StateAction::GoToPlaying {
                             song: song.clone(),
                             chart: chart.clone(),
+                            modifiers: self.modifiers.clone(),
+                            rate: self.rate,
                         };
                     } else if !song.charts.is_empty() {
                         // 进入选谱模式
@@ -90,6 +134,29 @@ impl Stateful for CollectionState {
                 }
                 StateAction::None
             }
+            Char('M' | 'm') if self.is_selecting_chart => {
+                self.toggle_modifier(Modifier::Mirror);
+                StateAction::None
+            }
+            Char('R' | 'r') if self.is_selecting_chart => {
+                self.toggle_modifier(Modifier::Random { seed: new_seed() });
+                StateAction::None
+            }
+            Char('H' | 'h') if self.is_selecting_chart => {
+                self.toggle_modifier(Modifier::Shuffle { seed: new_seed() });
+                StateAction::None
+            }
+            Char('N' | 'n') if self.is_selecting_chart => {
+                self.toggle_modifier(Modifier::NoHolds);
+                StateAction::None
+            }
+            Char('[' | ']') if self.is_selecting_chart => {
+                let step = if event.code == Char('[') { -RATE_STEP } else { RATE_STEP };
+                // 按步长取整，避免浮点累积误差
+                self.rate = ((self.rate + step) / RATE_STEP).round() * RATE_STEP;
+                self.rate = self.rate.clamp(MIN_RATE, MAX_RATE);
+                StateAction::None
+            }
             _ => StateAction::None,
         }
     }
@@ -97,4 +164,21 @@ impl Stateful for CollectionState {
     fn draw(&self, ctx: &AppContext, f: &mut Frame) {
         ui::collection::draw_collection(self, ctx, f)
     }
+
+    fn tick(&mut self, ctx: &AppContext, _dt: Duration) -> StateAction {
+        let focused = self.focused_chart();
+        if self.analysis.as_ref().map(|(key, _)| *key) != focused {
+            self.analysis = focused.and_then(|(s, c)| {
+                let chart = ctx.songs.get(s)?.charts.get(c)?;
+                Some(((s, c), analyze(chart)))
+            });
+        }
+        if self.ratings.as_ref().map(|(song, _)| *song) != self.song_cursor {
+            self.ratings = self.song_cursor.and_then(|s| {
+                let song = ctx.songs.get(s)?;
+                Some((s, song.charts.iter().map(estimate_difficulty).collect()))
+            });
+        }
+        StateAction::None
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::app::AppContext;
 use crate::core::chart::{Chart, ChartMeta};
-use crate::core::judge::{JudgeManager, JudgeResult};
+use crate::core::judge::{JudgeEvent, JudgeResult};
+use crate::core::modifier::{apply_all, Modifier};
+use crate::core::session::{GameSession, InputSource, KeyInput};
 use crate::core::timing::Time;
 use crate::models::{Song, SongAsset, SongMeta};
 use crate::rank::Rank;
@@ -9,9 +11,15 @@ use crate::ui;
 use ratatui::Frame;
 use ratatui::crossterm::event::KeyCode::{Char, Enter, Esc};
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
-use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+/// 要显示的最新判定，tick 和空按太密，不覆盖判定文字
+fn latest_judge(events: &[JudgeEvent]) -> Option<JudgeResult> {
+    events.iter().rev()
+        .map(|e| e.result)
+        .find(|r| !matches!(r, JudgeResult::Tick { .. } | JudgeResult::Empty))
+}
+
 #[derive(Copy, Clone, PartialOrd, PartialEq)]
 pub enum PlayingPhase {
     Ready,
@@ -22,60 +30,58 @@ pub enum PlayingPhase {
 
 pub struct PlayingState {
     pub elapsed_time: Time,
+    clock_at: Instant, // elapsed_time 最近一次更新的时刻
     pub phase: PlayingPhase,
     pub song_meta: SongMeta,
     pub song_asset: SongAsset, // 存储 asset 引用以便触发 StartAudio
     pub chart_meta: ChartMeta,
-    pub combo: u32,
-    pub max_combo: u32,
-    pub score: u32,
-    pub max_theoretical_score: u32,
-    pub perfect_count: u32,
-    pub good_count: u32,
-    pub miss_count: u32,
-    pub manager: JudgeManager,
+    pub session: GameSession, // 判定与计分
     pub last_judge: Option<(JudgeResult, Instant)>,
-    pub key_pressed: HashMap<u8, bool>,
     pub debug_logs: Vec<String>,
-    pub is_autoplay: bool
+    pub is_autoplay: bool,
+    autoplay: Option<Box<dyn InputSource>>, // 代替键盘按键的 bot
+    pub modifiers: Vec<Modifier>,
+    pub rate: f64, // 播放倍速，谱面时间已按此缩放
+    pub bgm: Vec<(Time, String)>, // 背景 keysound，按时间排序
+    pub bgm_cursor: usize,
 }
 
 impl PlayingState {
-    pub fn new(s: Song, c: &Chart, ctx: &AppContext) -> Self {
+    pub fn new(s: Song, c: &Chart, modifiers: Vec<Modifier>, rate: f64, ctx: &AppContext) -> anyhow::Result<Self> {
+        let c = &apply_all(c, &modifiers);
         let start_offset = ctx.global_config.playing.ready_seconds; // 2秒倒计时,为正
-        let total_notes: usize = c.tracks.iter().map(|t| t.notes.len()).sum();
-        let max_score = (total_notes * 1000) as u32;
-        let mut man = JudgeManager::new(
-            c.tracks.clone(),
-            c.timing_map.clone(),
-            ctx.global_config.playing.judge_core,
-        );
-        man.judges.sort_by_key(|j| j.id);
+        let mut session = GameSession::new(c, ctx.global_config.playing.judge_core.clone(), rate)?;
+
+        let mut bgm: Vec<(Time, String)> = c.bgm.iter()
+            .map(|e| (session.manager.map.beat_to_time(&e.beat), e.sound.clone()))
+            .collect();
+        bgm.sort_by(|a, b| a.0.0.total_cmp(&b.0.0));
         
-        let mut key_pressed = HashMap::new();
         for &track_idx in ctx.global_config.playing.keybind.values() {
-            key_pressed.insert(track_idx, false);
+            session.key_pressed.insert(track_idx, false);
         }
+        let is_autoplay = ctx.global_config.playing.autoplay;
+        let autoplay = is_autoplay.then(|| {
+            Box::new(ctx.global_config.playing.bot.play(&session.manager)) as Box<dyn InputSource>
+        });
 
-        Self {
+        Ok(Self {
             elapsed_time: Time(-start_offset),
+            clock_at: Instant::now(),
             phase: PlayingPhase::Ready,
             song_meta: s.meta,
             song_asset: s.asset,
             chart_meta: c.meta.clone(),
-            combo: 0,
-            max_combo: 0,
-            score: 0,
-            max_theoretical_score: max_score,
-            perfect_count: 0,
-            good_count: 0,
-            miss_count: 0,
-            manager: man,
+            session,
             last_judge: None,
-            key_pressed,
             debug_logs: vec![],
-            is_autoplay: ctx.global_config.playing.autoplay
-        }
+            is_autoplay,
+            autoplay,
+            modifiers,
+            rate,
+            bgm,
+            bgm_cursor: 0,
+        })
     }
 
     pub fn toggle_pause(&mut self) {
@@ -92,68 +98,61 @@ impl PlayingState {
 
     pub fn sync_audio_time(&mut self, audio_time: Duration, offset_ms: i32) {
         if self.phase == PlayingPhase::Playing {
-            // 核心公式：游戏逻辑时间 = 音频硬件时间 + 偏置
-            let offset_secs = offset_ms as f64 / 1000.0;
-            self.elapsed_time = Time(audio_time.as_secs_f64() + offset_secs);
+            self.elapsed_time = self.song_time(audio_time, offset_ms);
+            self.clock_at = Instant::now();
         }
     }
 
+    fn song_time(&self, audio_time: Duration, offset_ms: i32) -> Time {
+        // 核心公式：游戏逻辑时间 = 歌曲位置 / 倍速 + 偏置
+        // 谱面时间已按倍速缩放，偏置是设备延迟，不随倍速变化
+        let offset_secs = offset_ms as f64 / 1000.0;
+        Time(audio_time.as_secs_f64() / self.rate + offset_secs)
+    }
+
+    /// 输入线程的时间戳换算成谱面时间：播放中直接问音频时钟，倒计时从上次更新 elapsed_time 的时刻外推
+    fn time_at(&self, ctx: &AppContext, at: Instant) -> Time {
+        if self.phase == PlayingPhase::Playing {
+            return self.song_time(ctx.audio.pos_at(at), ctx.global_config.playing.global_offset_ms);
+        }
+        let since = if at >= self.clock_at {
+            at.duration_since(self.clock_at).as_secs_f64()
+        } else {
+            -self.clock_at.duration_since(at).as_secs_f64()
+        };
+        Time(self.elapsed_time.0 + since)
+    }
+
     // 辅助函数，让 UI 层获取纯秒数
     pub fn current_time(&self) -> f64 {
         self.elapsed_time.0
     }
 
-    fn process_judge_result(&mut self, ctx: &AppContext, result: JudgeResult) {
-        self.last_judge = Some((result, Instant::now()));
-        match result {
-            JudgeResult::Perfect(_) => {
-                // ctx.audio.play_hit_effect();
-                self.perfect_count += 1;
-                self.score += 1000;
-                self.combo += 1;
-                self.max_combo = self.max_combo.max(self.combo);
-            }
-            JudgeResult::Good(_) => {
-                // ctx.audio.play_hit_effect();
-                self.good_count += 1;
-                self.score += 500;
-                self.combo += 1;
-                self.max_combo = self.max_combo.max(self.combo);
-            }
-            JudgeResult::Miss => {
-                self.miss_count += 1;
-                self.combo = 0;
-            }
+    /// 按下时播放该轨道下一个待击打音符的 keysound (判定之前调用)
+    fn play_keysound(&self, ctx: &AppContext, track: u8) {
+        let Some(judge) = self.session.manager.judges.iter().find(|j| j.id == track) else { return };
+        if let Some(sound) = judge.next_playable().and_then(|i| judge.notes[i].sound()) {
+            ctx.audio.play_keysound(sound);
         }
     }
 
-    pub fn get_accuracy_pct(&self) -> f64 {
-        if self.max_theoretical_score == 0 {
-            return 0.0;
+    /// 键盘和 autoplay 共用：新按下时先播 keysound 再判定
+    fn press(&mut self, ctx: &AppContext, input: KeyInput) {
+        // 按住时的重复按下不发声，也不判定
+        if input.is_down && !self.session.key_pressed.get(&input.track).copied().unwrap_or(false) {
+            self.play_keysound(ctx, input.track);
         }
-        (self.score as f64 / self.max_theoretical_score as f64) * 101.0
-    }
-
-    /// 计算当前理论最高准度 (Potential Accuracy)
-    /// 逻辑：(当前分数 + 剩余音符全部 Perfect 的分数) / 总分
-    pub fn get_potential_accuracy_pct(&self) -> f64 {
-        if self.max_theoretical_score == 0 {
-            return 101.0;
+        if let Some(result) = latest_judge(self.session.on_key(input)) {
+            self.last_judge = Some((result, Instant::now()));
         }
+    }
 
-        // 1. 获取所有轨道中还未被判定的音符总数
-        let remaining_notes: usize = self
-            .manager
-            .judges
-            .iter()
-            .map(|j| j.notes.len().saturating_sub(j.cursor))
-            .sum();
-
-        // 2. 假设剩下的全是 Perfect (每个 1000 分)
-        let potential_score = self.score + (remaining_notes as u32 * 1000);
+    pub fn get_accuracy_pct(&self) -> f64 {
+        self.session.accuracy_pct()
+    }
 
-        // 3. 映射到 101.0 基准
-        (potential_score as f64 / self.max_theoretical_score as f64) * 101.0
+    pub fn get_potential_accuracy_pct(&self) -> f64 {
+        self.session.potential_accuracy_pct()
     }
 
     /// 获取当前分数的评价等级
@@ -176,9 +175,9 @@ impl PlayingState {
 }
 
 impl Stateful for PlayingState {
-    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent) -> StateAction {
-        self.log_event(event.code, event.kind, self.elapsed_time.0);
-        let now = self.elapsed_time;
+    fn handle_input(&mut self, ctx: &AppContext, event: KeyEvent, at: Instant) -> StateAction {
+        let now = self.time_at(ctx, at);
+        self.log_event(event.code, event.kind, now.0);
         let is_down = match event.kind {
             KeyEventKind::Press => true,
             KeyEventKind::Release => false,
@@ -207,9 +206,11 @@ impl Stateful for PlayingState {
                     return if self.phase == PlayingPhase::Ready {
                         // 如果玩家在倒计时按确定，可以视为“直接开始”
                         self.elapsed_time = Time(0.0);
+                        self.clock_at = Instant::now();
                         self.phase = PlayingPhase::Playing;
                         StateAction::StartAudio {
                             song_asset: self.song_asset.clone(),
+                            rate: self.rate,
                         }
                     } else {
                         StateAction::TogglePause
@@ -226,21 +227,7 @@ impl Stateful for PlayingState {
                 let track_idx = ctx.global_config.playing.keybind.get(&c).copied();
 
                 if let Some(idx) = track_idx {
-                    if is_down {
-                        // 使用 entry 确保安全访问并检查状态
-                        let pressed = self.key_pressed.entry(idx).or_insert(false);
-                        if !*pressed {
-                            *pressed = true;
-                            if let Some(res) = self.manager.on_input(idx, now, true) {
-                                self.process_judge_result(ctx, res);
-                            }
-                        }
-                    } else {
-                        self.key_pressed.insert(idx, false);
-                        if let Some(res) = self.manager.on_input(idx, now, false) {
-                            self.process_judge_result(ctx, res);
-                        }
-                    }
+                    self.press(ctx, KeyInput { time: now, track: idx, is_down });
                 }
             }
             _ => {}
@@ -256,6 +243,7 @@ impl Stateful for PlayingState {
         match self.phase {
             PlayingPhase::Ready => {
                 self.elapsed_time.0 += dt.as_secs_f64();
+                self.clock_at = Instant::now();
                 // 为了平滑过渡到 Playing, 在这里要处理好 Offset
                 let start_threshold = ctx.global_config.playing.global_offset_ms as f64 / 1000.0;
 
@@ -264,6 +252,7 @@ impl Stateful for PlayingState {
                     self.phase = PlayingPhase::Playing;
                     return StateAction::StartAudio {
                         song_asset: self.song_asset.clone(),
+                        rate: self.rate,
                     };
                 }
                 StateAction::None
@@ -271,62 +260,34 @@ impl Stateful for PlayingState {
             PlayingPhase::Playing => {
                 let now = self.elapsed_time;
 
-                // 1. 先处理 Autoplay，把产生的判定结果存入一个临时 Vec
-                if self.is_autoplay {
-                    let mut autoplay_results = Vec::new();
-
-                    // 🚩 只借用 manager，不借用整个 self
-                    for judge in &mut self.manager.judges {
-                        if let Some(note) = judge.notes.get(judge.cursor) {
-                            let note_time = self.manager.map.beat_to_time(&note.beat());
-
-                            if now >= note_time {
-                                match note {
-                                    crate::core::chart::Note::Tap { .. } => {
-                                        if let Some(res) = judge.on_input(now, true, &self.manager.core, &self.manager.map) {
-                                            autoplay_results.push(res);
-                                        }
-                                    }
-                                    crate::core::chart::Note::Hold { end, .. } => {
-                                        let end_time = self.manager.map.beat_to_time(end);
-                                        let state = judge.states[judge.cursor];
-
-                                        if state == crate::core::judge::NoteState::Pending {
-                                            judge.on_input(now, true, &self.manager.core, &self.manager.map);
-                                        } else if now >= end_time {
-                                            if let Some(res) = judge.on_input(now, false, &self.manager.core, &self.manager.map) {
-                                                autoplay_results.push(res);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                // 1. Autoplay 的按键和键盘走同一条路径
+                if let Some(source) = &mut self.autoplay {
+                    for input in source.poll(now) {
+                        self.press(ctx, input);
                     }
+                }
 
-                    // 2. Autoplay 处理完释放了 manager 的借用，现在可以安全调用 self 的方法了
-                    for res in autoplay_results {
-                        self.process_judge_result(ctx, res);
+                // 背景 keysound 到点播放
+                while let Some((time, sound)) = self.bgm.get(self.bgm_cursor) {
+                    if *time > now {
+                        break;
                     }
+                    ctx.audio.play_keysound(sound);
+                    self.bgm_cursor += 1;
                 }
 
-                // 3. 处理正常的更新（如自动 Miss）
-                // 这里你的代码原本就已经是先 update 拿结果，再循环处理，所以这部分通常是没问题的
-                let updates = self.manager.update(self.elapsed_time);
-                for update in updates {
-                    self.process_judge_result(ctx, update.result);
+                // 2. 处理正常的更新（如自动 Miss）
+                if let Some(result) = latest_judge(self.session.update(self.elapsed_time)) {
+                    self.last_judge = Some((result, Instant::now()));
                 }
 
                 // 检查音频结束
                 if ctx.audio.is_finished() {
-                    let remaining_misses = self.manager.clear_and_count_unjudged();
-                    for _ in 0..remaining_misses {
-                        self.process_judge_result(ctx, JudgeResult::Miss);
-                    }
+                    self.session.finish();
 
                     self.phase = PlayingPhase::Finished;
                     return StateAction::ShowResult {
-                        score: self.score,
+                        score: self.session.score,
                         rank: Rank::from_percentage(self.get_accuracy_pct()),
                     };
                 }
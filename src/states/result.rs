@@ -1,5 +1,7 @@
 use crate::app::AppContext;
 use crate::core::chart::ChartMeta;
+use crate::core::judge::JudgeTier;
+use crate::core::modifier::Modifier;
 use crate::models::{SongMeta};
 use crate::states::playing::PlayingState;
 use crate::states::{StateAction, Stateful};
@@ -8,22 +10,31 @@ use ratatui::Frame;
 use ratatui::crossterm::event::KeyCode::{Char, Esc};
 use ratatui::crossterm::event::KeyEvent;
 use crate::rank::Rank;
+use std::time::Instant;
 
 pub struct ResultState {
-    pub score: u32,
+    pub score: u64,
     pub is_autoplay: bool,
     pub max_combo: u32,
-    pub perfect_count: u32,
-    pub good_count: u32,
+    pub tiers: Vec<JudgeTier>,
+    pub tier_counts: Vec<u32>,
     pub miss_count: u32,
+    pub poor_count: u32,
+    pub tick_count: u32,
+    pub tick_miss_count: u32,
+    pub mine_hit_count: u32,
+    pub empty_count: u32,
+    pub gauge: f64,
     pub rank: Rank,
     pub accuracy: f64, // 0.0..=101.0
     pub song_meta: SongMeta,
     pub chart_meta: ChartMeta,
+    pub modifiers: Vec<Modifier>,
+    pub rate: f64,
 }
 
 impl Stateful for ResultState {
-    fn handle_input(&mut self, _ctx: &AppContext, event: KeyEvent) -> StateAction {
+    fn handle_input(&mut self, _ctx: &AppContext, event: KeyEvent, _at: Instant) -> StateAction {
         match event.code {
             Char('Q' | 'q') | Esc => StateAction::GoToCollection,
             _ => StateAction::None,
@@ -36,18 +47,26 @@ impl Stateful for ResultState {
 }
 
 impl ResultState {
-    pub(crate) fn from_playing(p: &PlayingState, score: u32, rank: Rank) -> Self {
+    pub(crate) fn from_playing(p: &PlayingState, score: u64, rank: Rank) -> Self {
         Self {
             score,
             is_autoplay: p.is_autoplay,
-            max_combo: p.max_combo,
-            perfect_count: p.perfect_count,
-            good_count: p.good_count,
-            miss_count: p.miss_count,
+            max_combo: p.session.max_combo,
+            tiers: p.session.manager.core.window.tiers().to_vec(),
+            tier_counts: p.session.tier_counts.clone(),
+            miss_count: p.session.miss_count,
+            poor_count: p.session.poor_count,
+            tick_count: p.session.tick_count,
+            tick_miss_count: p.session.tick_miss_count,
+            mine_hit_count: p.session.mine_hit_count,
+            empty_count: p.session.empty_count,
+            gauge: p.session.gauge,
             rank,
             accuracy: p.get_accuracy_pct(),
             song_meta: p.song_meta.clone(),
             chart_meta: p.chart_meta.clone(),
+            modifiers: p.modifiers.clone(),
+            rate: p.rate,
         }
     }
 }
@@ -1,13 +1,14 @@
 use ratatui::crossterm::event::KeyCode::{Char, Enter, Esc};
 use ratatui::crossterm::event::KeyEvent;
 use ratatui::Frame;
+use std::time::Instant;
 use crate::app::AppContext;
 use crate::states::{StateAction, Stateful};
 use crate::ui;
 
 pub struct WelcomeState;
 impl Stateful for WelcomeState {
-    fn handle_input(&mut self, _context: &AppContext, event: KeyEvent) -> StateAction {
+    fn handle_input(&mut self, _context: &AppContext, event: KeyEvent, _at: Instant) -> StateAction {
         match event.code {
             Char('Q' | 'q') | Esc => StateAction::Quit,
             Enter | Char(' ' | '\n') => StateAction::GoToCollection,
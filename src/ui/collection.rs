@@ -1,4 +1,5 @@
 use crate::app::AppContext;
+use crate::core::analysis::ChartAnalysis;
 use crate::states::collection::CollectionState;
 use ratatui::{prelude::*, widgets::*};
 
@@ -56,14 +57,17 @@ pub fn draw_collection(state: &CollectionState, ctx: &AppContext, f: &mut Frame)
 
         // 3. 右侧：详情与谱面选择
         if let Some(idx) = state.song_cursor {
-            render_song_details(&ctx.songs[idx], f, content_chunks[1], state);
+            let analysis = state.analysis.as_ref()
+                .filter(|((s, _), _)| *s == idx)
+                .map(|(_, a)| a);
+            render_song_details(&ctx.songs[idx], analysis, f, content_chunks[1], state);
         }
     }
 
     // 4. 底部提示条
     render_hint_bar(state, f, main_chunks[2]);
 }
-fn render_song_details(song: &crate::models::Song, f: &mut Frame, area: Rect, state: &CollectionState) {
+fn render_song_details(song: &crate::models::Song, analysis: Option<&ChartAnalysis>, f: &mut Frame, area: Rect, state: &CollectionState) {
     let border_color = if state.is_selecting_chart { Color::Yellow } else { Color::White };
     let details_block = Block::default()
         .borders(Borders::ALL)
@@ -74,9 +78,14 @@ fn render_song_details(song: &crate::models::Song, f: &mut Frame, area: Rect, st
     let inner_area = details_block.inner(area);
     f.render_widget(details_block, area);
 
-    // 拆分详情区：上部文字，下部谱面列表
+    // 拆分详情区：歌曲信息、谱面统计、密度图、谱面列表
     let detail_chunks = Layout::default()
-        .constraints([Constraint::Length(7), Constraint::Min(0)])
+        .constraints([
+            Constraint::Length(4),
+            Constraint::Length(5),
+            Constraint::Length(4),
+            Constraint::Min(0),
+        ])
         .split(inner_area);
 
     let info_text = vec![
@@ -95,13 +104,17 @@ fn render_song_details(song: &crate::models::Song, f: &mut Frame, area: Rect, st
     ];
     f.render_widget(Paragraph::new(info_text), detail_chunks[0]);
 
+    if let Some(analysis) = analysis {
+        render_chart_stats(analysis, f, detail_chunks[1], detail_chunks[2]);
+    }
+
     // 谱面选择区
     if song.charts.is_empty() {
         f.render_widget(
             Paragraph::new("! No charts available for this song !")
                 .style(Style::default().fg(Color::Red))
                 .alignment(Alignment::Center),
-            detail_chunks[1]
+            detail_chunks[3]
         );
     } else {
         let charts: Vec<ListItem> = song.charts.iter().enumerate().map(|(i, chart)| {
@@ -113,14 +126,71 @@ fn render_song_details(song: &crate::models::Song, f: &mut Frame, area: Rect, st
             };
 
             let symbol = if is_selected { "> " } else { "  " };
-            ListItem::new(format!("{}[LV.{:02}] charter: {} {}", symbol, chart.meta.level, chart.meta.charter, chart.meta.desc)).style(style)
+            let rating = state.ratings.as_ref()
+                .filter(|(s, _)| Some(*s) == state.song_cursor)
+                .and_then(|(_, r)| r.get(i))
+                .map(|r| format!(" ~{:.1}", r))
+                .unwrap_or_default();
+            ListItem::new(format!("{}[LV.{:02}{}] charter: {} {}", symbol, chart.meta.level, rating, chart.meta.charter, chart.meta.desc)).style(style)
         }).collect();
 
         let chart_list = List::new(charts)
             .block(Block::default().title(" Select Difficulty ").borders(Borders::TOP));
-        f.render_widget(chart_list, detail_chunks[1]);
+        f.render_widget(chart_list, detail_chunks[3]);
     }
 }
+
+fn render_chart_stats(analysis: &ChartAnalysis, f: &mut Frame, stats_area: Rect, density_area: Rect) {
+    let label = Style::default().fg(Color::Gray);
+    let drain = analysis.drain_time.0.max(0.0) as u64;
+
+    let chords = analysis.chords.iter()
+        .map(|(size, count)| format!("{size}x{count}"))
+        .collect::<Vec<_>>()
+        .join("  ");
+    let lanes = analysis.lane_load.iter()
+        .map(|(_, count)| format!("{:.0}%", *count as f64 * 100.0 / analysis.total_notes.max(1) as f64))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let stats_text = vec![
+        Line::from(vec![
+            Span::styled("Notes:  ", label),
+            Span::styled(format!("{}", analysis.total_notes), Style::default().fg(Color::Cyan)),
+            Span::raw(if analysis.roll_count > 0 {
+                format!(" (Tap {} / Hold {} / Roll {})", analysis.tap_count, analysis.hold_count, analysis.roll_count)
+            } else {
+                format!(" (Tap {} / Hold {})", analysis.tap_count, analysis.hold_count)
+            }),
+        ]),
+        Line::from(vec![
+            Span::styled("Drain:  ", label),
+            Span::raw(format!("{}:{:02}", drain / 60, drain % 60)),
+        ]),
+        Line::from(vec![
+            Span::styled("NPS:    ", label),
+            Span::raw(format!("avg {:.1} / ", analysis.average_nps())),
+            Span::styled(format!("peak {:.1}", analysis.peak_nps), Style::default().fg(Color::LightRed)),
+        ]),
+        Line::from(vec![Span::styled("Chords: ", label), Span::raw(chords)]),
+        Line::from(vec![Span::styled("Lanes:  ", label), Span::raw(lanes)]),
+    ];
+    f.render_widget(Paragraph::new(stats_text), stats_area);
+
+    // 密度图：按可用宽度对 NPS 采样分桶取最大值
+    let width = density_area.width.max(1) as usize;
+    let bucket = analysis.nps.len().div_ceil(width).max(1);
+    let data: Vec<u64> = analysis.nps.chunks(bucket)
+        .map(|c| c.iter().copied().fold(0.0, f64::max).round() as u64)
+        .collect();
+    f.render_widget(
+        Sparkline::default()
+            .block(Block::default().title(" Density ").borders(Borders::TOP))
+            .data(&data)
+            .style(Style::default().fg(Color::Yellow)),
+        density_area
+    );
+}
 fn render_empty_details(f: &mut Frame, area: Rect) {
     let msg = Paragraph::new("Select a song to see details")
         .alignment(Alignment::Center)
@@ -141,11 +211,13 @@ fn render_empty_details(f: &mut Frame, area: Rect) {
 }
 fn render_hint_bar(state: &CollectionState, f: &mut Frame, area: Rect) {
     let hint = if state.is_selecting_chart {
-        " [UP/DOWN] Change Chart  [ENTER] Play  [ESC/Q] Cancel "
+        let mods = state.modifiers.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ");
+        format!(" [UP/DOWN] Change Chart  [ENTER] Play  [ESC/Q] Cancel  [M]irror [R]andom S[H]uffle [N]oHold: {}  [[/]] Rate: {:.2}x ",
+                if mods.is_empty() { "-" } else { &mods }, state.rate)
     } else if state.song_cursor.is_some() {
-        " [UP/DOWN] Select Song  [ENTER] Choose Chart  [ESC/Q] Back "
+        " [UP/DOWN] Select Song  [ENTER] Choose Chart  [ESC/Q] Back ".to_string()
     } else {
-        " [ESC] Quit "
+        " [ESC] Quit ".to_string()
     };
 
     let p = Paragraph::new(hint)
@@ -1,6 +1,6 @@
 use crate::app::AppContext;
 use crate::core::chart::Note;
-use crate::core::judge::NoteState;
+use crate::core::judge::{JudgeResult, JudgeTier, NoteState};
 use crate::states::playing::{PlayingPhase, PlayingState};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Padding, Paragraph};
@@ -13,7 +13,7 @@ pub fn draw_playing(
 ) {
     let area = f.area();
 
-    let track_count = state.manager.judges.len() as u16;
+    let track_count = state.session.manager.judges.len() as u16;
     let play_panel_width = (track_count * ctx.global_config.playing.track_width) + 2;
 
     let main_chunks = Layout::default()
@@ -59,12 +59,12 @@ fn draw_info_panel(state: &PlayingState, f: &mut Frame, area: Rect) {
     f.render_widget(Paragraph::new(info), inner);
 }
 
-/// 基于 Time(f64) 的线性坐标映射
+/// 基于视觉位置 (scroll time 对 SV 积分) 的线性坐标映射，停顿 (stop) 期间画面冻结
 ///
-fn calculate_y(note_time: f64, current_time: f64, judgment_line_y: u16, speed: f64) -> i32 {
-    let time_diff = note_time - current_time;
-    // time_diff > 0 表示音符在未来，y 值应小于判定线（在上方）
-    judgment_line_y as i32 - (time_diff * speed) as i32
+fn calculate_y(note_pos: f64, current_pos: f64, judgment_line_y: u16, speed: f64) -> i32 {
+    let pos_diff = note_pos - current_pos;
+    // pos_diff > 0 表示音符在未来，y 值应小于判定线（在上方）
+    judgment_line_y as i32 - (pos_diff * speed) as i32
 }
 
 fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64) {
@@ -82,8 +82,9 @@ fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64)
     let inner_area = block.inner(area);
     f.render_widget(block, area);
 
-    let now = state.current_time();
-    let track_count = state.manager.judges.len() as u16;
+    let map = &state.session.manager.map;
+    let now = map.time_to_visual(&state.elapsed_time);
+    let track_count = state.session.manager.judges.len() as u16;
     if track_count == 0 { return; }
 
     // 🚩 修复 2: 根据实际轨道数分配宽度
@@ -92,41 +93,61 @@ fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64)
     let judgment_line_y = inner_area.bottom().saturating_sub(1);
 
     // 1. 绘制轨道背景 (当按键按下时亮起)
-    for (t_idx, judge) in state.manager.judges.iter().enumerate() {
+    for (t_idx, judge) in state.session.manager.judges.iter().enumerate() {
         let x = inner_area.x + (t_idx as u16 * track_width);
-        if state.key_pressed.get(&judge.id).copied().unwrap_or(false) {
+        if state.session.key_pressed.get(&judge.id).copied().unwrap_or(false) {
             f.render_widget(
                 Block::default().bg(Color::Indexed(234)),
                 Rect::new(x, inner_area.y, track_width, inner_area.height)
             );
         }
     }
-    // 2. 绘制判定线
+    // 2. 绘制小节线 (贯穿所有轨道，颜色较淡)
+    let top_pos = now + (judgment_line_y - inner_area.top()) as f64 / speed;
+    let (mut measure, _) = map.beat_to_measure(&map.time_to_beat(&state.elapsed_time));
+    // SV 可能让小节线非单调，设个上限防止死循环
+    for _ in 0..64 {
+        let pos = map.beat_to_visual(&map.measure_to_beat(measure));
+        if pos > top_pos {
+            break;
+        }
+        let y = calculate_y(pos, now, judgment_line_y, speed);
+        if y >= inner_area.top() as i32 && y < judgment_line_y as i32 {
+            f.render_widget(
+                Paragraph::new("─".repeat(inner_area.width as usize))
+                    .style(Style::default().fg(Color::Indexed(238))),
+                Rect::new(inner_area.x, y as u16, inner_area.width, 1)
+            );
+        }
+        measure += 1;
+    }
+
+    // 3. 绘制判定线
     f.render_widget(
         Paragraph::new("━".repeat(inner_area.width as usize))
             .style(Style::default().fg(Color::Cyan)),
         Rect::new(inner_area.x, judgment_line_y, inner_area.width, 1)
     );
 
-    // 3. 遍历轨道绘制音符
+    // 4. 遍历轨道绘制音符
     let note_padding = 1;
     let visual_note_width = track_width.saturating_sub(note_padding * 2);
 
-    for (t_idx, judge) in state.manager.judges.iter().enumerate() {
+    for (t_idx, judge) in state.session.manager.judges.iter().enumerate() {
         let track_x = inner_area.x + (t_idx as u16 * track_width);
         // 音符实际绘制的起始 X 坐标
         let note_x = track_x + note_padding;
 
         for (n_idx, note) in judge.notes.iter().enumerate() {
             let note_state = judge.states[n_idx];
-            if !matches!(note_state, NoteState::Pending | NoteState::Holding(_)) {
+            if !matches!(note_state, NoteState::Pending | NoteState::Holding(_) | NoteState::Rolling(..)) {
                 continue;
             }
 
             match note {
-                Note::Tap { beat } => {
-                    let note_time = state.manager.map.beat_to_time(beat).0;
-                    let y = calculate_y(note_time, now, judgment_line_y, speed);
+                Note::Tap { beat, .. } => {
+                    let note_pos = map.beat_to_visual(beat);
+                    let y = calculate_y(note_pos, now, judgment_line_y, speed);
 
                     if y >= inner_area.top() as i32 && y <= judgment_line_y as i32 {
                         let symbol = "━".repeat(visual_note_width as usize);
@@ -139,16 +160,16 @@ fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64)
                         );
                     }
                 }
-                Note::Hold { start, end } => {
-                    let start_time = state.manager.map.beat_to_time(start).0;
-                    let end_time = state.manager.map.beat_to_time(end).0;
+                Note::Hold { start, end, .. } => {
+                    let start_pos = map.beat_to_visual(start);
+                    let end_pos = map.beat_to_visual(end);
 
                     let y_start = if matches!(note_state, NoteState::Holding(_)) {
                         judgment_line_y as i32
                     } else {
-                        calculate_y(start_time, now, judgment_line_y, speed)
+                        calculate_y(start_pos, now, judgment_line_y, speed)
                     };
-                    let y_end = calculate_y(end_time, now, judgment_line_y, speed);
+                    let y_end = calculate_y(end_pos, now, judgment_line_y, speed);
 
                     let draw_top = y_end.max(inner_area.top() as i32);
                     let draw_bottom = y_start.min(judgment_line_y as i32);
@@ -181,20 +202,70 @@ fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64)
                         );
                     }
                 }
+                Note::Roll { start, end, .. } => {
+                    let rolling = matches!(note_state, NoteState::Rolling(..));
+                    let y_start = if rolling {
+                        judgment_line_y as i32
+                    } else {
+                        calculate_y(map.beat_to_visual(start), now, judgment_line_y, speed)
+                    };
+                    let y_end = calculate_y(map.beat_to_visual(end), now, judgment_line_y, speed);
+
+                    let draw_top = y_end.max(inner_area.top() as i32);
+                    let draw_bottom = y_start.min(judgment_line_y as i32);
+
+                    // Roll 身体用锯齿线，与 Hold 区分
+                    let body_color = if rolling { Color::LightMagenta } else { Color::Magenta };
+                    let roll_symbol = "╱╲".repeat(visual_note_width as usize / 2 + 1);
+                    for y_fill in draw_top..draw_bottom {
+                        let offset = (y_fill - y_end).rem_euclid(2) as usize * "╱".len();
+                        f.render_widget(
+                            Paragraph::new(&roll_symbol[offset..]).style(Style::default().fg(body_color)),
+                            Rect::new(note_x, y_fill as u16, visual_note_width, 1)
+                        );
+                    }
+
+                    if !rolling && y_start >= inner_area.top() as i32 && y_start <= judgment_line_y as i32 {
+                        let head_symbol = "▆".repeat(visual_note_width as usize);
+                        f.render_widget(
+                            Paragraph::new(head_symbol).style(Style::default().fg(Color::Magenta)),
+                            Rect::new(note_x, y_start as u16, visual_note_width, 1)
+                        );
+                    }
+                }
+                Note::Mine { beat } => {
+                    let y = calculate_y(map.beat_to_visual(beat), now, judgment_line_y, speed);
+
+                    if y >= inner_area.top() as i32 && y <= judgment_line_y as i32 {
+                        // 地雷：红色叉号，与 Tap 明显区分
+                        let symbol = "╳".repeat(visual_note_width as usize);
+                        f.render_widget(
+                            Paragraph::new(symbol).style(Style::default().fg(Color::Red)),
+                            Rect::new(note_x, y as u16, visual_note_width, 1)
+                        );
+                    }
+                }
             }
         }
     }
 
-    // 4. 判定反馈文字
+    // 5. 判定反馈文字
     if let Some((result, time)) = state.last_judge {
         if time.elapsed() < Duration::from_millis(500) {
+            // 非最高档额外提示提前/滞后
+            let mut timing = None;
             let (text, color) = match result {
-                crate::core::judge::JudgeResult::Perfect(_) => (" PERFECT ", Color::LightYellow),
-                crate::core::judge::JudgeResult::Good(d) => {
-                    if d.0 < 0.0   {("  EARLY   ", Color::LightBlue)}
-                    else           {("   LATE   ", Color::LightRed)}
-                },
-                crate::core::judge::JudgeResult::Miss => ("  MISS   ", Color::Gray),
+                JudgeResult::Hit { tier: idx, delta } => {
+                    let tier = &state.session.manager.core.window.tiers()[idx];
+                    if idx > 0 {
+                        timing = Some(if delta.0 < 0.0 { ("EARLY", Color::LightBlue) } else { ("LATE", Color::LightRed) });
+                    }
+                    (tier.name.as_str(), tier_color(tier))
+                }
+                JudgeResult::Poor { .. } => ("POOR", Color::LightMagenta),
+                JudgeResult::Miss => ("MISS", Color::Gray),
+                JudgeResult::MineHit => ("MINE!", Color::Red),
+                JudgeResult::Tick { .. } | JudgeResult::Empty => ("", Color::Reset), // 不会成为 last_judge
             };
 
             let judge_y = judgment_line_y.saturating_sub(4);
@@ -204,6 +275,14 @@ fn draw_play_panel(state: &PlayingState, f: &mut Frame, area: Rect, speed: f64)
                     .style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
                 Rect::new(inner_area.x, judge_y, inner_area.width, 1)
             );
+            if let Some((text, color)) = timing {
+                f.render_widget(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Center)
+                        .style(Style::default().fg(color)),
+                    Rect::new(inner_area.x, judge_y + 1, inner_area.width, 1)
+                );
+            }
         }
     }
 }
@@ -223,6 +302,7 @@ fn draw_stats_panel(
             Constraint::Length(4),
             Constraint::Length(1),
             Constraint::Length(4),
+            Constraint::Length(state.session.tier_counts.len() as u16 + 3),
             Constraint::Min(2),
         ])
         .split(area);
@@ -244,30 +324,79 @@ fn draw_stats_panel(
         ]),
         Line::from(vec![
             Span::styled("SCORE", Style::default().fg(Color::DarkGray)),
-            Span::styled(format!("{:07}", state.score), Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{:07}", state.session.score), Style::default().fg(Color::Cyan)),
+        ]),
+        Line::from(vec![
+            Span::styled("LIFE ", Style::default().fg(Color::DarkGray)),
+            Span::styled(gauge_bar(state.session.gauge, 10), Style::default().fg(gauge_color(state.session.gauge))),
         ]),
     ];
     if state.is_autoplay {
         let autoplay_label = Paragraph::new("AUTO-PLAY ENABLED")
             .alignment(Alignment::Left)
             .style(Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC));
-        f.render_widget(autoplay_label, chunks[4]);
+        f.render_widget(autoplay_label, chunks[5]);
     }
 
     f.render_widget(Paragraph::new(stats), chunks[3]);
+
+    // 各档判定计数
+    let mut counts: Vec<Line> = state.session.manager.core.window.tiers().iter()
+        .zip(&state.session.tier_counts)
+        .map(|(tier, count)| Line::from(vec![
+            Span::styled(format!("{:<10}", tier.name), Style::default().fg(tier_color(tier))),
+            Span::raw(count.to_string()),
+        ]))
+        .collect();
+    counts.push(Line::from(vec![
+        Span::styled(format!("{:<10}", "MISS"), Style::default().fg(Color::Gray)),
+        Span::raw(state.session.miss_count.to_string()),
+    ]));
+    if state.session.poor_count > 0 {
+        counts.push(Line::from(vec![
+            Span::styled(format!("{:<10}", "POOR"), Style::default().fg(Color::LightMagenta)),
+            Span::raw(state.session.poor_count.to_string()),
+        ]));
+    }
+    if state.session.empty_count > 0 {
+        counts.push(Line::from(vec![
+            Span::styled(format!("{:<10}", "EMPTY"), Style::default().fg(Color::DarkGray)),
+            Span::raw(state.session.empty_count.to_string()),
+        ]));
+    }
+    f.render_widget(Paragraph::new(counts), chunks[4]);
+}
+
+/// 生命值条，`gauge` 为 0..=1
+fn gauge_bar(gauge: f64, width: usize) -> String {
+    let filled = ((gauge * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+fn gauge_color(gauge: f64) -> Color {
+    match gauge {
+        g if g >= 0.5 => Color::Green,
+        g if g >= 0.2 => Color::Yellow,
+        _ => Color::Red,
+    }
+}
+
+/// 判定档的颜色，配置里写错时退回白色
+pub fn tier_color(tier: &JudgeTier) -> Color {
+    tier.color.parse().unwrap_or(Color::White)
 }
 
 fn draw_combo_panel(state: &PlayingState, f: &mut Frame, area: Rect) {
-    if state.combo == 0 { return; }
+    if state.session.combo == 0 { return; }
 
-    let (base_color, modifier) = match state.combo {
+    let (base_color, modifier) = match state.session.combo {
         c if c >= 500 => (Color::Magenta, Modifier::BOLD | Modifier::ITALIC),
         c if c >= 100 => (Color::LightYellow, Modifier::BOLD),
         _ => (Color::Gray, Modifier::DIM),
     };
 
     let display_color = if let Some((result, time)) = state.last_judge {
-        if !matches!(result, crate::core::judge::JudgeResult::Miss) && time.elapsed() < Duration::from_millis(100) {
+        if !matches!(result, JudgeResult::Miss | JudgeResult::MineHit) && time.elapsed() < Duration::from_millis(100) {
             Color::White
         } else {
             base_color
@@ -277,7 +406,7 @@ fn draw_combo_panel(state: &PlayingState, f: &mut Frame, area: Rect) {
     };
 
     f.render_widget(
-        Paragraph::new(format!("{}\nCOMBO", state.combo))
+        Paragraph::new(format!("{}\nCOMBO", state.session.combo))
             .style(Style::default().fg(display_color).add_modifier(modifier))
             .alignment(Alignment::Left),
         area
@@ -1,4 +1,5 @@
 use crate::states::result::ResultState;
+use crate::ui::playing::tier_color;
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Padding, Paragraph};
 
@@ -75,7 +76,19 @@ pub fn draw_result(state: &ResultState, f: &mut Frame) {
         )]));
         stats_text.push(Line::from(""));
     }
-    // 我们将 Perfect, Good, Miss 渲染得更像统计表
+    if !state.modifiers.is_empty() || state.rate != 1.0 {
+        let mut mods: Vec<String> = state.modifiers.iter().map(|m| m.to_string()).collect();
+        if state.rate != 1.0 {
+            mods.insert(0, format!("{:.2}x", state.rate));
+        }
+        let mods = mods.join(" ");
+        stats_text.push(Line::from(vec![
+            Span::raw(" MODS     "),
+            Span::styled(mods, Style::default().fg(Color::LightMagenta)),
+        ]));
+        stats_text.push(Line::from(""));
+    }
+    // 我们将各档判定渲染得更像统计表
     stats_text.extend(vec![
         Line::from(vec![
             Span::raw(" SCORE    "),
@@ -100,20 +113,52 @@ pub fn draw_result(state: &ResultState, f: &mut Frame) {
                 Style::default().fg(Color::Green),
             ),
         ]),
-        Line::from("-".repeat(30)).style(Style::default().fg(Color::DarkGray)),
-        // 判定统计
-        Line::from(vec![
-            Span::styled(" PERFECT  ", Style::default().fg(Color::Cyan)),
-            Span::raw(format!(" {:3}", state.perfect_count)),
-        ]),
         Line::from(vec![
-            Span::styled(" GOOD     ", Style::default().fg(Color::Green)),
-            Span::raw(format!(" {:3}", state.good_count)),
-        ]),
-        Line::from(vec![
-            Span::styled(" MISS     ", Style::default().fg(Color::Red)),
-            Span::raw(format!(" {:3}", state.miss_count)),
+            Span::raw(" LIFE     "),
+            Span::styled(
+                format!("{:.0}%", state.gauge * 100.0),
+                Style::default().fg(Color::LightGreen),
+            ),
         ]),
+        Line::from("-".repeat(30)).style(Style::default().fg(Color::DarkGray)),
+    ]);
+    // 判定统计，每档一行
+    for (tier, count) in state.tiers.iter().zip(&state.tier_counts) {
+        stats_text.push(Line::from(vec![
+            Span::styled(format!(" {:<9}", tier.name), Style::default().fg(tier_color(tier))),
+            Span::raw(format!(" {:3}", count)),
+        ]));
+    }
+    stats_text.push(Line::from(vec![
+        Span::styled(" MISS     ", Style::default().fg(Color::Red)),
+        Span::raw(format!(" {:3}", state.miss_count)),
+    ]));
+    if state.poor_count > 0 {
+        stats_text.push(Line::from(vec![
+            Span::styled(" POOR     ", Style::default().fg(Color::LightMagenta)),
+            Span::raw(format!(" {:3}", state.poor_count)),
+        ]));
+    }
+    let total_ticks = state.tick_count + state.tick_miss_count;
+    if total_ticks > 0 {
+        stats_text.push(Line::from(vec![
+            Span::styled(" TICK     ", Style::default().fg(Color::LightGreen)),
+            Span::raw(format!(" {:3}/{}", state.tick_count, total_ticks)),
+        ]));
+    }
+    if state.mine_hit_count > 0 {
+        stats_text.push(Line::from(vec![
+            Span::styled(" MINE     ", Style::default().fg(Color::LightRed)),
+            Span::raw(format!(" {:3}", state.mine_hit_count)),
+        ]));
+    }
+    if state.empty_count > 0 {
+        stats_text.push(Line::from(vec![
+            Span::styled(" EMPTY    ", Style::default().fg(Color::DarkGray)),
+            Span::raw(format!(" {:3}", state.empty_count)),
+        ]));
+    }
+    stats_text.extend(vec![
         Line::from(""),
         Line::from(" [Q/Esc] Back to Collection ")
             .style(Style::default().add_modifier(Modifier::REVERSED)),